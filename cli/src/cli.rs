@@ -1,35 +1,1264 @@
 //! Command-line interface flags and parameters.
 
+use crate::{config, error::Error, mnemonic};
+
+use std::ffi::OsString;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{
+    parser::ValueSource, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum,
+};
 use http::uri;
+use serde::Serialize;
 
 /// Accepted arguments by the command-line application.
 #[derive(Parser)]
-#[command(author, version, about)]
+#[command(author, version, about, after_help = EXIT_CODES_HELP)]
 pub struct App {
+    /// Format of the output of the results and the errors
+    #[arg(
+        long,
+        short = 'o',
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Table
+    )]
+    pub output: OutputFormat,
+    /// Configuration file with the profiles. By default, 'ipfs-cids-owners/config.toml' in the
+    /// user's configuration directory
+    #[arg(long, global = true, env = ENV_CONFIG)]
+    pub config: Option<PathBuf>,
+    /// Profile of the configuration file whose values are used for the flags which aren't passed
+    /// through the command-line nor their environment variables. By default, the profile set as
+    /// default in the configuration file
+    #[arg(long, global = true, env = ENV_PROFILE)]
+    pub profile: Option<String>,
+    #[command(flatten)]
+    pub retry: RetryArgs,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Environment variables of the flags.
+const ENV_CONFIG: &str = "IPFS_CIDS_OWNERS_CONFIG";
+const ENV_PROFILE: &str = "IPFS_CIDS_OWNERS_PROFILE";
+const ENV_ETHER_CHAIN_ID: &str = "IPFS_CIDS_OWNERS_ETHER_CHAIN_ID";
+const ENV_ETHER_CONTRACT_ADDRESS: &str = "IPFS_CIDS_OWNERS_ETHER_CONTRACT_ADDRESS";
+const ENV_ETHER_ENDPOINT: &str = "IPFS_CIDS_OWNERS_ETHER_ENDPOINT";
+const ENV_ETHER_OWNER_PRIV_KEY: &str = "IPFS_CIDS_OWNERS_ETHER_OWNER_PRIV_KEY";
+const ENV_KEYSTORE: &str = "IPFS_CIDS_OWNERS_KEYSTORE";
+const ENV_PASSWORD_FILE: &str = "IPFS_CIDS_OWNERS_PASSWORD_FILE";
+const ENV_MNEMONIC_FILE: &str = "IPFS_CIDS_OWNERS_MNEMONIC_FILE";
+const ENV_DERIVATION_PATH: &str = "IPFS_CIDS_OWNERS_DERIVATION_PATH";
+const ENV_ETHER_NODE_ACCOUNT: &str = "IPFS_CIDS_OWNERS_ETHER_NODE_ACCOUNT";
+const ENV_REMOTE_SIGNER: &str = "IPFS_CIDS_OWNERS_REMOTE_SIGNER";
+const ENV_REMOTE_SIGNER_ACCOUNT: &str = "IPFS_CIDS_OWNERS_REMOTE_SIGNER_ACCOUNT";
+const ENV_PKCS11_MODULE: &str = "IPFS_CIDS_OWNERS_PKCS11_MODULE";
+const ENV_PKCS11_SLOT: &str = "IPFS_CIDS_OWNERS_PKCS11_SLOT";
+const ENV_PKCS11_LABEL: &str = "IPFS_CIDS_OWNERS_PKCS11_LABEL";
+const ENV_TX_TYPE: &str = "IPFS_CIDS_OWNERS_TX_TYPE";
+const ENV_MAX_FEE: &str = "IPFS_CIDS_OWNERS_MAX_FEE";
+const ENV_PRIORITY_FEE: &str = "IPFS_CIDS_OWNERS_PRIORITY_FEE";
+const ENV_GAS_PRICE: &str = "IPFS_CIDS_OWNERS_GAS_PRICE";
+const ENV_GAS_LIMIT_MULTIPLIER: &str = "IPFS_CIDS_OWNERS_GAS_LIMIT_MULTIPLIER";
+const ENV_FEE_CAP: &str = "IPFS_CIDS_OWNERS_FEE_CAP";
+const ENV_PENDING_TIMEOUT: &str = "IPFS_CIDS_OWNERS_PENDING_TIMEOUT";
+const ENV_FEE_BUMP: &str = "IPFS_CIDS_OWNERS_FEE_BUMP";
+const ENV_MAX_REPLACEMENTS: &str = "IPFS_CIDS_OWNERS_MAX_REPLACEMENTS";
+const ENV_CONFIRMATIONS: &str = "IPFS_CIDS_OWNERS_CONFIRMATIONS";
+const ENV_FINALIZED: &str = "IPFS_CIDS_OWNERS_FINALIZED";
+const ENV_IPFS_ENDPOINT: &str = "IPFS_CIDS_OWNERS_IPFS_ENDPOINT";
+const ENV_MAX_ATTEMPTS: &str = "IPFS_CIDS_OWNERS_MAX_ATTEMPTS";
+const ENV_RETRY_BACKOFF: &str = "IPFS_CIDS_OWNERS_RETRY_BACKOFF";
+const ENV_CALL_TIMEOUT: &str = "IPFS_CIDS_OWNERS_CALL_TIMEOUT";
+const ENV_RETRY_TIMEOUT: &str = "IPFS_CIDS_OWNERS_RETRY_TIMEOUT";
+const ENV_AUTH_TOKEN: &str = "IPFS_CIDS_OWNERS_AUTH_TOKEN";
+
+/// Documentation of the exit codes shown in the help.
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  Success
+  2  Invalid usage of the flags (e.g. an unknown flag or a value of a wrong format)
+  3  Error reported by an external system (IPFS or Ethereum)
+  4  Internal error
+  5  Invalid arguments (e.g. a file which doesn't exist or an invalid configuration file)";
+
+/// Subcommands offered by the command-line application.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Upload a file to IPFS and register its CID to the CIDsOwners smart contract
+    UploadAndRegister(UploadAndRegister),
+    /// Resume a run of the upload-and-register subcommand which failed or didn't wait for its
+    /// transactions, skipping its finished steps and the transactions which were already sent
+    Resume(Resume),
+    /// Upload a file to IPFS without registering its CID
+    Upload(Upload),
+    /// Register CIDs, which are already in IPFS, to the CIDsOwners smart contract
+    Register(Register),
+    /// Upload the files listed in a manifest file (CSV or JSON) to IPFS and register their CIDs
+    /// to the CIDsOwners smart contract
+    Batch(Batch),
+    /// Watch a directory and upload to IPFS and register to the CIDsOwners smart contract the
+    /// files that are added or changed, until it's stopped
+    Watch(Watch),
+    /// Run an HTTP server which exposes the upload and registration of files, and the queries of
+    /// CIDs and transactions through a REST API
+    Serve(Serve),
+    /// Run a gRPC server which exposes the upload and registration of files, and the queries of
+    /// CIDs and transactions
+    ServeGrpc(ServeGrpc),
+    /// List the CIDs registered by an owner in the CIDsOwners smart contract
+    List(List),
+    /// Download the content of a CID from IPFS after checking that it's registered by an owner in
+    /// the CIDsOwners smart contract
+    Fetch(Fetch),
+    /// Verify if the CID of a local file is registered by an owner in the CIDsOwners smart
+    /// contract
+    Verify(Verify),
+    /// Manage the pins of IPFS
+    #[command(subcommand)]
+    Pin(PinCommand),
+    /// Inspect Ethereum transactions sent by the application
+    #[command(subcommand)]
+    Tx(TxCommand),
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Manage the encrypted keystores of the CIDs' owners keys
+    #[command(subcommand)]
+    Keys(KeysCommand),
+}
+
+/// Subcommands of the keys subcommand.
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// Create a keystore with a new random private key
+    New(KeysNew),
+    /// Create a keystore with an existing private key, which is prompted or read from a file
+    Import(KeysImport),
+    /// List the keystores of a directory and their addresses
+    List(KeysList),
+    /// List the addresses derived from a mnemonic for a range of account indices
+    Derive(KeysDerive),
+}
+
+/// Subcommands of the config subcommand.
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Show the effective settings, after applying the flags, environment variables and profile,
+    /// and where each one comes from. Secrets are redacted
+    Show(ConfigShow),
+}
+
+/// Subcommands of the pin subcommand.
+#[derive(Subcommand)]
+pub enum PinCommand {
+    /// Pin CIDs in IPFS
+    Add(PinCids),
+    /// Unpin CIDs from IPFS
+    Rm(PinCids),
+    /// List the CIDs pinned in IPFS, excluding the indirect ones
+    Ls(PinLs),
+    /// Verify that the CIDs registered by an owner in the CIDsOwners smart contract are pinned in
+    /// IPFS
+    Verify(PinVerify),
+}
+
+/// Arguments of the upload-and-register subcommand.
+#[derive(Args)]
+pub struct UploadAndRegister {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub owner: OwnerArgs,
+    #[command(flatten)]
+    pub gas: GasArgs,
+    #[command(flatten)]
+    pub tx: TxArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    #[command(flatten)]
+    pub dir: DirArgs,
+    #[command(flatten)]
+    pub journal: JournalArgs,
+    /// Register the CIDs of all the entries of the directory besides the root one when uploading
+    /// a directory
+    #[arg(long)]
+    pub register_children: bool,
+    /// Compute the CIDs without storing anything in IPFS and simulate the registrations without
+    /// sending any transaction, estimating their gas and cost
+    #[arg(long, conflicts_with = "no_wait")]
+    pub dry_run: bool,
+
+    // Positional arguments.
+    /// The file or directory to upload
+    pub filepath: PathBuf,
+    /// The path to set for the uploaded file
+    #[arg(value_parser = validate_remote_path)]
+    pub remote_path: Option<String>,
+}
+
+/// Arguments of the resume subcommand. The file or directory and the options for uploading and
+/// registering it are the ones of the resumed run.
+#[derive(Args)]
+pub struct Resume {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub owner: OwnerArgs,
+    #[command(flatten)]
+    pub gas: GasArgs,
+    #[command(flatten)]
+    pub tx: TxArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    #[command(flatten)]
+    pub journal: JournalArgs,
+
+    // Positional arguments.
+    /// ID of the run to resume. By default, the latest run of the owner which isn't finished
+    pub id: Option<String>,
+}
+
+/// Arguments of the upload subcommand.
+#[derive(Args)]
+pub struct Upload {
+    // Flags.
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    #[command(flatten)]
+    pub dir: DirArgs,
+
+    // Positional arguments.
+    /// The file or directory to upload
+    pub filepath: PathBuf,
+    /// The path to set for the uploaded file
+    #[arg(value_parser = validate_remote_path)]
+    pub remote_path: Option<String>,
+}
+
+/// Arguments of the register subcommand.
+#[derive(Args)]
+pub struct Register {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub owner: OwnerArgs,
+    #[command(flatten)]
+    pub gas: GasArgs,
+    #[command(flatten)]
+    pub tx: TxArgs,
+    /// Simulate the registrations without sending any transaction, estimating their gas and cost
+    #[arg(long, conflicts_with = "no_wait")]
+    pub dry_run: bool,
+
+    // Positional arguments.
+    /// The CIDs to register. They are registered in the same order
+    #[arg(required = true, value_parser = validate_cid)]
+    pub cids: Vec<String>,
+}
+
+/// Arguments of the batch subcommand.
+#[derive(Args)]
+pub struct Batch {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub owner: OwnerArgs,
+    #[command(flatten)]
+    pub gas: GasArgs,
+    #[command(flatten)]
+    pub confirmation: ConfirmationArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    /// Maximum number of files uploaded at the same time
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+    /// Path of the manifest file (CSV or JSON) where the results are written. By default, it's
+    /// the manifest path with the '-result' suffix in the file name
+    #[arg(long, short = 'r')]
+    pub result: Option<PathBuf>,
+    /// Compute the CIDs without storing anything in IPFS and simulate the registrations without
+    /// sending any transaction, estimating their gas and cost. The result manifest isn't written
+    #[arg(long)]
+    pub dry_run: bool,
+
+    // Positional arguments.
+    /// Manifest file with the 'filepath' and optional 'remote_path' fields of each file. Relative
+    /// file paths are relative to the manifest file directory
+    pub manifest: PathBuf,
+}
+
+/// Arguments of the watch subcommand.
+#[derive(Args)]
+pub struct Watch {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub owner: OwnerArgs,
+    #[command(flatten)]
+    pub gas: GasArgs,
+    #[command(flatten)]
+    pub tx: TxArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    /// File where the already registered files are recorded. By default, it's the
+    /// '.ipfs-cids-owners-watch.json' file in the watched directory
+    #[arg(long)]
+    pub state: Option<PathBuf>,
+    /// Milliseconds without changes in the directory before processing the changed files, so files
+    /// aren't uploaded while they are being written
+    #[arg(long, default_value_t = 1000)]
+    pub settle_ms: u64,
+
+    // Positional arguments.
+    /// The directory to watch
+    pub dir: PathBuf,
+}
+
+/// Arguments of the serve subcommand.
+#[derive(Args)]
+pub struct Serve {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub owner: OwnerArgs,
+    #[command(flatten)]
+    pub gas: GasArgs,
+    #[command(flatten)]
+    pub tx: TxArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    /// Address where the server listens. Format <ip>:<port>
+    #[arg(long, short = 'l', default_value = "127.0.0.1:8000")]
+    pub listen: std::net::SocketAddr,
+    /// Token that the requests must send as a bearer token. Requests aren't authenticated when it
+    /// isn't set
+    #[arg(long, env = ENV_AUTH_TOKEN, hide_env_values = true)]
+    pub auth_token: Option<String>,
+    /// Maximum size in bytes of the uploaded files
+    #[arg(long, default_value_t = 100 * 1024 * 1024)]
+    pub max_upload_size: usize,
+}
+
+/// Arguments of the serve-grpc subcommand.
+#[derive(Args)]
+pub struct ServeGrpc {
     // Flags.
-    #[arg(long, short = 'c', default_value_t = 1)]
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub owner: OwnerArgs,
+    #[command(flatten)]
+    pub gas: GasArgs,
+    #[command(flatten)]
+    pub confirmation: ConfirmationArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    /// Address where the server listens. Format <ip>:<port>
+    #[arg(long, short = 'l', default_value = "127.0.0.1:50051")]
+    pub listen: std::net::SocketAddr,
+    /// Token that the requests must send as a bearer token in the authorization metadata.
+    /// Requests aren't authenticated when it isn't set
+    #[arg(long, env = ENV_AUTH_TOKEN, hide_env_values = true)]
+    pub auth_token: Option<String>,
+}
+
+/// Arguments of the list subcommand.
+#[derive(Args)]
+pub struct List {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    /// Number of CIDs to skip from the beginning of the list
+    #[arg(long, default_value_t = 0)]
+    pub offset: usize,
+    /// Maximum number of CIDs to show. All of them when it isn't set
+    #[arg(long)]
+    pub limit: Option<usize>,
+    // Positional arguments.
+    /// Ethereum address of the CIDs' owner. Format 0x....
+    #[arg(value_parser = validate_ether_address)]
+    pub owner_address: String,
+}
+
+/// Arguments of the verify subcommand.
+#[derive(Args)]
+pub struct Verify {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+
+    // Positional arguments.
+    pub filepath: PathBuf,
+    /// Ethereum address of the CID's owner. Format 0x....
+    #[arg(value_parser = validate_ether_address)]
+    pub owner_address: String,
+}
+
+/// Arguments of the fetch subcommand.
+#[derive(Args)]
+pub struct Fetch {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    /// Ethereum address of the CID's owner. Format 0x....
+    #[arg(long, value_parser = validate_ether_address)]
+    pub owner: String,
+    /// Download the content, showing a warning, when the CID isn't registered by the owner
+    #[arg(long)]
+    pub allow_unregistered: bool,
+    /// File where the content is written. The standard output when it isn't set
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    // Positional arguments.
+    /// The CID to download
+    #[arg(value_parser = validate_cid)]
+    pub cid: String,
+}
+
+/// Subcommands of the tx subcommand.
+#[derive(Subcommand)]
+pub enum TxCommand {
+    /// Show the status of a transaction and the CID that it registers
+    Status(TxStatus),
+    /// Replace a pending transaction of the owner with the same one with higher fees
+    Speedup(TxReplace),
+    /// Replace a pending transaction of the owner with a transfer of 0 ether to itself with higher
+    /// fees, so the pending transaction isn't mined
+    Cancel(TxReplace),
+}
+
+/// Arguments of the pin add and pin rm subcommands.
+#[derive(Args)]
+pub struct PinCids {
+    // Flags.
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    #[command(flatten)]
+    pub filter: OwnerFilterArgs,
+
+    // Positional arguments.
+    /// The CIDs. When the owner is set, only the ones registered by the owner are used and all of
+    /// them if none is passed
+    #[arg(required_unless_present = "owner", value_parser = validate_cid)]
+    pub cids: Vec<String>,
+}
+
+/// Arguments of the pin ls subcommand.
+#[derive(Args)]
+pub struct PinLs {
+    // Flags.
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    #[command(flatten)]
+    pub filter: OwnerFilterArgs,
+}
+
+/// Arguments of the pin verify subcommand.
+#[derive(Args)]
+pub struct PinVerify {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub ipfs: IpfsArgs,
+    /// Pin the registered CIDs that aren't pinned
+    #[arg(long)]
+    pub repair: bool,
+
+    // Positional arguments.
+    /// Ethereum address of the CIDs' owner. Format 0x....
+    #[arg(value_parser = validate_ether_address)]
+    pub owner_address: String,
+}
+
+/// Arguments of the tx status subcommand.
+#[derive(Args)]
+pub struct TxStatus {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+
+    // Positional arguments.
+    /// Hash of the transaction. Format 0x....
+    #[arg(value_parser = validate_tx_hash)]
+    pub hash: String,
+}
+
+/// Arguments of the tx speedup and tx cancel subcommands.
+#[derive(Args)]
+pub struct TxReplace {
+    // Flags.
+    #[command(flatten)]
+    pub ether: EthereumArgs,
+    #[command(flatten)]
+    pub owner: OwnerArgs,
+    #[command(flatten)]
+    pub gas: GasArgs,
+    #[command(flatten)]
+    pub tx: TxArgs,
+
+    // Positional arguments.
+    /// Hash of the pending transaction. Format 0x....
+    #[arg(value_parser = validate_tx_hash)]
+    pub hash: String,
+}
+
+/// Arguments of the keys new subcommand.
+#[derive(Args)]
+pub struct KeysNew {
+    // Flags.
+    #[command(flatten)]
+    pub dir: KeysDirArgs,
+    /// Name of the keystore file. By default, the address of the key
+    #[arg(long)]
+    pub name: Option<String>,
+    /// File which contains the password of the keystore. When it isn't set, the password is read
+    /// from the IPFS_CIDS_OWNERS_KEYSTORE_PASSWORD environment variable or prompted
+    #[arg(long)]
+    pub password_file: Option<PathBuf>,
+}
+
+/// Arguments of the keys import subcommand.
+#[derive(Args)]
+pub struct KeysImport {
+    // Flags.
+    #[command(flatten)]
+    pub dir: KeysDirArgs,
+    /// Name of the keystore file. By default, the address of the key
+    #[arg(long)]
+    pub name: Option<String>,
+    /// File which contains the password of the keystore. When it isn't set, the password is read
+    /// from the IPFS_CIDS_OWNERS_KEYSTORE_PASSWORD environment variable or prompted
+    #[arg(long)]
+    pub password_file: Option<PathBuf>,
+    /// File which contains the private key to import. Format 0x.... or without it. When it isn't
+    /// set, the private key is prompted
+    #[arg(long)]
+    pub priv_key_file: Option<PathBuf>,
+}
+
+/// Arguments of the keys list subcommand.
+#[derive(Args)]
+pub struct KeysList {
+    // Flags.
+    #[command(flatten)]
+    pub dir: KeysDirArgs,
+}
+
+/// Arguments of the keys derive subcommand.
+#[derive(Args)]
+pub struct KeysDerive {
+    // Flags.
+    /// File which contains the BIP-39 mnemonic phrase (English word list)
+    #[arg(long)]
+    pub mnemonic_file: PathBuf,
+    /// Derivation path prefix to which the account indices are appended
+    #[arg(long, default_value = mnemonic::DEFAULT_PATH_PREFIX, value_parser = validate_derivation_path_prefix)]
+    pub path_prefix: String,
+    /// First account index of the range
+    #[arg(long, default_value_t = 0)]
+    pub from: u32,
+    /// Number of account indices of the range
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=1000))]
+    pub count: u32,
+}
+
+/// Flags for selecting the keystores directory.
+#[derive(Args)]
+pub struct KeysDirArgs {
+    /// Directory of the keystores. By default, 'ipfs-cids-owners/keys' in the user's
+    /// configuration directory
+    #[arg(long)]
+    pub keys_dir: Option<PathBuf>,
+}
+
+/// Flags for selecting the journals directory.
+#[derive(Args)]
+pub struct JournalArgs {
+    /// Directory of the journals of the upload-and-register runs. By default,
+    /// 'ipfs-cids-owners/journals' in the user's configuration directory
+    #[arg(long)]
+    pub journal_dir: Option<PathBuf>,
+}
+
+/// Arguments of the config show subcommand. It doesn't have flags; [`parse`] resolves the
+/// settings of the flags of all the subcommands.
+#[derive(Args)]
+pub struct ConfigShow {
+    /// The configuration file which was loaded. Set by [`parse`].
+    #[arg(skip)]
+    pub config_file: Option<PathBuf>,
+    /// The profile which was applied. Set by [`parse`].
+    #[arg(skip)]
+    pub profile: Option<String>,
+    /// The settings of the flags which can be set by the environment variables and the profiles.
+    /// Set by [`parse`].
+    #[arg(skip)]
+    pub settings: Vec<Setting>,
+}
+
+/// The effective value of a flag and where it comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Setting {
+    /// The long name of the flag.
+    pub name: String,
+    /// `None` when the flag isn't set.
+    pub value: Option<String>,
+    /// `None` when the flag isn't set.
+    pub source: Option<SettingSource>,
+    /// Whether the value is secret (e.g. a private key), so it must not be shown.
+    pub secret: bool,
+}
+
+/// Where the value of a flag comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingSource {
+    Flag,
+    Env,
+    Profile,
+    Default,
+}
+
+impl std::fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingSource::Flag => write!(f, "flag"),
+            SettingSource::Env => write!(f, "env"),
+            SettingSource::Profile => write!(f, "profile"),
+            SettingSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Flags for accessing the CIDsOwners smart contract.
+#[derive(Args)]
+pub struct EthereumArgs {
+    #[arg(long, short = 'c', default_value_t = 1, env = ENV_ETHER_CHAIN_ID)]
     pub ehter_chain_id: u64,
-    #[arg(long, short = 'a', value_parser = validate_ether_address)]
+    #[arg(long, short = 'a', value_parser = validate_ether_address, env = ENV_ETHER_CONTRACT_ADDRESS)]
     pub ether_contract_address: String,
     /// Ethereum endpoint. Format http(s)?://<host>:<port>
-    #[arg(long, short = 'e')]
+    #[arg(long, short = 'e', env = ENV_ETHER_ENDPOINT)]
     pub ether_endpoint: Endpoint,
+}
+
+/// Flags for filtering the CIDs to the ones registered by an owner in the CIDsOwners smart
+/// contract. The flags for accessing the smart contract are only required when the owner is set,
+/// which is checked when the command runs because they can be set by a configuration profile.
+#[derive(Args)]
+pub struct OwnerFilterArgs {
+    /// Ethereum address of the CIDs' owner. Format 0x....
+    #[arg(long, value_parser = validate_ether_address)]
+    pub owner: Option<String>,
+    #[arg(long, short = 'c', default_value_t = 1, env = ENV_ETHER_CHAIN_ID)]
+    pub ehter_chain_id: u64,
+    #[arg(long, short = 'a', value_parser = validate_ether_address, env = ENV_ETHER_CONTRACT_ADDRESS)]
+    pub ether_contract_address: Option<String>,
+    /// Ethereum endpoint. Format http(s)?://<host>:<port>
+    #[arg(long, short = 'e', env = ENV_ETHER_ENDPOINT)]
+    pub ether_endpoint: Option<Endpoint>,
+}
+
+/// Flags for identifying the CIDs' owner that signs the transactions.
+/// The owner's key is a raw private key, an encrypted keystore or derived from a mnemonic, or the
+/// transactions are signed outside of the application.
+#[derive(Args)]
+pub struct OwnerArgs {
     /// Ethereum private key of the CID's owner. Format 0x.... or without it
-    #[arg(long, short = 'p', value_parser = validate_ether_private_key)]
-    pub ether_owner_priv_key: String,
+    #[arg(
+        long,
+        short = 'p',
+        value_parser = validate_ether_private_key,
+        env = ENV_ETHER_OWNER_PRIV_KEY,
+        hide_env_values = true,
+        hide_default_value = true,
+        required_unless_present_any = OWNER_KEY_SOURCES,
+        conflicts_with_all = OWNER_KEY_SOURCES
+    )]
+    pub ether_owner_priv_key: Option<String>,
+    #[command(flatten)]
+    pub keystore: KeystoreArgs,
+    #[command(flatten)]
+    pub mnemonic: MnemonicArgs,
+    #[command(flatten)]
+    pub external_signer: ExternalSignerArgs,
+    #[command(flatten)]
+    pub pkcs11: Pkcs11Args,
+}
 
-    #[arg(long, short = 'i')]
+/// IDs of the flags which set the CIDs' owner and their environment variables.
+const OWNER_FLAGS: [(&str, &str); 11] = [
+    ("ether_owner_priv_key", ENV_ETHER_OWNER_PRIV_KEY),
+    ("keystore", ENV_KEYSTORE),
+    ("password_file", ENV_PASSWORD_FILE),
+    ("mnemonic_file", ENV_MNEMONIC_FILE),
+    ("derivation_path", ENV_DERIVATION_PATH),
+    ("ether_node_account", ENV_ETHER_NODE_ACCOUNT),
+    ("remote_signer", ENV_REMOTE_SIGNER),
+    ("remote_signer_account", ENV_REMOTE_SIGNER_ACCOUNT),
+    ("pkcs11_module", ENV_PKCS11_MODULE),
+    ("pkcs11_slot", ENV_PKCS11_SLOT),
+    ("pkcs11_label", ENV_PKCS11_LABEL),
+];
+
+/// IDs of the flags which provide the owner's key, apart from the private key.
+const OWNER_KEY_SOURCES: [&str; 5] = [
+    "keystore",
+    "mnemonic_file",
+    "ether_node_account",
+    "remote_signer",
+    "pkcs11_module",
+];
+
+/// Flags for using an encrypted keystore (Web3 Secret Storage format) as the CIDs' owner key.
+#[derive(Args)]
+pub struct KeystoreArgs {
+    /// Encrypted JSON keystore with the private key of the CID's owner. A file name is also looked
+    /// up in the default keystores directory (see the keys subcommand)
+    #[arg(
+        long,
+        short = 'k',
+        env = ENV_KEYSTORE,
+        conflicts_with_all = ["mnemonic_file", "ether_node_account", "remote_signer", "pkcs11_module"]
+    )]
+    pub keystore: Option<PathBuf>,
+    /// File which contains the password of the keystore. When it isn't set, the password is read
+    /// from the IPFS_CIDS_OWNERS_KEYSTORE_PASSWORD environment variable or prompted
+    #[arg(long, env = ENV_PASSWORD_FILE, requires = "keystore")]
+    pub password_file: Option<PathBuf>,
+}
+
+/// Flags for deriving the CIDs' owner key from a BIP-39 mnemonic (English word list).
+#[derive(Args)]
+pub struct MnemonicArgs {
+    /// File which contains the BIP-39 mnemonic phrase from which the key of the CID's owner is
+    /// derived
+    #[arg(
+        long,
+        env = ENV_MNEMONIC_FILE,
+        conflicts_with_all = ["ether_node_account", "remote_signer", "pkcs11_module"]
+    )]
+    pub mnemonic_file: Option<PathBuf>,
+    /// Derivation path of the key. Format m/44'/60'/0'/0/0. By default, the path of the account
+    /// index
+    #[arg(
+        long,
+        env = ENV_DERIVATION_PATH,
+        requires = "mnemonic_file",
+        conflicts_with = "account_index",
+        value_parser = validate_derivation_path
+    )]
+    pub derivation_path: Option<String>,
+    /// Account index of the key, which is appended to the derivation path m/44'/60'/0'/0/
+    #[arg(long, requires = "mnemonic_file")]
+    pub account_index: Option<u32>,
+}
+
+/// Flags for signing the transactions of the CIDs' owner outside of the application, so its private
+/// key isn't on the machine that runs it.
+#[derive(Args)]
+pub struct ExternalSignerArgs {
+    /// Address of the CID's owner account managed (i.e. unlocked) by the Ethereum node, which
+    /// signs the transactions sent through eth_sendTransaction. Format 0x....
+    #[arg(
+        long,
+        value_parser = validate_ether_address,
+        env = ENV_ETHER_NODE_ACCOUNT,
+        conflicts_with_all = ["remote_signer", "pkcs11_module"]
+    )]
+    pub ether_node_account: Option<String>,
+    /// Web3Signer compatible remote signer which holds the key of the CID's owner.
+    /// Format http(s)?://<host>:<port>
+    #[arg(
+        long,
+        env = ENV_REMOTE_SIGNER,
+        requires = "remote_signer_account",
+        conflicts_with = "pkcs11_module"
+    )]
+    pub remote_signer: Option<Endpoint>,
+    /// Address of the CID's owner key held by the remote signer. Format 0x....
+    #[arg(
+        long,
+        value_parser = validate_ether_address,
+        env = ENV_REMOTE_SIGNER_ACCOUNT,
+        requires = "remote_signer"
+    )]
+    pub remote_signer_account: Option<String>,
+}
+
+/// Flags for signing the transactions of the CIDs' owner with a secp256k1 key held in a PKCS#11
+/// token (e.g. an HSM).
+#[derive(Args)]
+pub struct Pkcs11Args {
+    /// PKCS#11 module (i.e. shared library) of the token which holds the key of the CID's owner
+    #[arg(long, env = ENV_PKCS11_MODULE, requires_all = ["pkcs11_slot", "pkcs11_label"])]
+    pub pkcs11_module: Option<PathBuf>,
+    /// ID of the slot of the token
+    #[arg(long, env = ENV_PKCS11_SLOT, requires = "pkcs11_module")]
+    pub pkcs11_slot: Option<u64>,
+    /// Label of the private and public key objects of the key
+    #[arg(long, env = ENV_PKCS11_LABEL, requires = "pkcs11_module")]
+    pub pkcs11_label: Option<String>,
+    /// File which contains the user PIN of the token. When it isn't set, the PIN is read from the
+    /// IPFS_CIDS_OWNERS_PKCS11_PIN environment variable or prompted
+    #[arg(long, requires = "pkcs11_module")]
+    pub pkcs11_pin_file: Option<PathBuf>,
+}
+
+/// Flags for setting the type, the fees and the gas limit of the transactions that register CIDs.
+/// The fees which aren't set are estimated through the Ethereum node.
+#[derive(Args)]
+pub struct GasArgs {
+    /// Type of the transactions. Use legacy for the chains which don't support EIP-1559
+    #[arg(long, value_enum, default_value_t = TxType::Eip1559, env = ENV_TX_TYPE)]
+    pub tx_type: TxType,
+    /// Maximum fee per gas in gwei of type-2 transactions
+    #[arg(long, value_parser = validate_gwei, env = ENV_MAX_FEE)]
+    pub max_fee: Option<String>,
+    /// Maximum priority fee per gas (i.e. tip) in gwei of type-2 transactions
+    #[arg(long, value_parser = validate_gwei, env = ENV_PRIORITY_FEE)]
+    pub priority_fee: Option<String>,
+    /// Gas price in gwei of legacy transactions
+    #[arg(
+        long,
+        value_parser = validate_gwei,
+        env = ENV_GAS_PRICE,
+        conflicts_with_all = ["max_fee", "priority_fee"]
+    )]
+    pub gas_price: Option<String>,
+    /// Multiplier applied to the estimated gas for setting the gas limit of the transactions. It
+    /// must be greater than or equal to 1
+    #[arg(
+        long,
+        default_value_t = 1.0,
+        value_parser = validate_gas_limit_multiplier,
+        env = ENV_GAS_LIMIT_MULTIPLIER
+    )]
+    pub gas_limit_multiplier: f64,
+    /// Fee per gas in gwei above which the transactions aren't sent and the command fails. For
+    /// type-2 transactions, it's compared with the maximum fee per gas. It also applies to the
+    /// replacements of the pending transactions
+    #[arg(long, value_parser = validate_gwei, env = ENV_FEE_CAP)]
+    pub fee_cap: Option<String>,
+    /// Seconds that a transaction can be pending before replacing it with another one, with the
+    /// same nonce and higher fees. The transactions aren't replaced when it isn't set
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        env = ENV_PENDING_TIMEOUT
+    )]
+    pub pending_timeout: Option<u64>,
+    /// Percentage by which the fees of a replaced transaction are increased. The nodes require at
+    /// least 10
+    #[arg(
+        long,
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u64).range(10..),
+        env = ENV_FEE_BUMP
+    )]
+    pub fee_bump: u64,
+    /// Maximum number of times that a pending transaction is replaced before failing
+    #[arg(long, default_value_t = 3, env = ENV_MAX_REPLACEMENTS)]
+    pub max_replacements: u32,
+}
+
+/// Types of the transactions.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TxType {
+    /// Transactions with a gas price
+    Legacy,
+    /// EIP-1559 (i.e. type-2) transactions with a maximum fee and a maximum priority fee
+    Eip1559,
+}
+
+/// Flags for controlling the transactions that register CIDs.
+#[derive(Args)]
+pub struct TxArgs {
+    /// Don't wait until the transactions are mined; they can be followed with the tx status
+    /// subcommand
+    #[arg(long, conflicts_with_all = ["confirmations", "finalized"])]
+    pub no_wait: bool,
+    #[command(flatten)]
+    pub confirmation: ConfirmationArgs,
+}
+
+/// Flags for setting when a mined transaction that registers CIDs is considered final. After
+/// that, the transaction is checked to still be in the canonical chain, so it fails if a chain
+/// reorganization removed it.
+#[derive(Args)]
+pub struct ConfirmationArgs {
+    /// Number of blocks mined since the block which contains the transaction, including it, to
+    /// wait for
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..),
+        env = ENV_CONFIRMATIONS
+    )]
+    pub confirmations: u64,
+    /// Wait until the block which contains the transaction is finalized (i.e. the 'finalized'
+    /// block tag) instead of waiting for a number of confirmations
+    #[arg(long, env = ENV_FINALIZED, conflicts_with = "confirmations")]
+    pub finalized: bool,
+}
+
+/// Global flags for retrying and timing out the calls to IPFS and Ethereum which fail with
+/// transient errors (e.g. connection errors or rate limits).
+#[derive(Args, Clone)]
+pub struct RetryArgs {
+    /// Maximum number of attempts of each call to IPFS and Ethereum
+    #[arg(
+        long,
+        global = true,
+        default_value_t = 3,
+        value_parser = clap::value_parser!(u32).range(1..),
+        env = ENV_MAX_ATTEMPTS
+    )]
+    pub max_attempts: u32,
+    /// Milliseconds to wait before the second attempt of a call. It's doubled for each following
+    /// attempt, up to 30 seconds, and a random jitter is applied
+    #[arg(long, global = true, default_value_t = 500, env = ENV_RETRY_BACKOFF)]
+    pub retry_backoff: u64,
+    /// Seconds after which an attempt of a call times out. 0 disables it. The uploads to IPFS
+    /// aren't timed out
+    #[arg(long, global = true, default_value_t = 120, env = ENV_CALL_TIMEOUT)]
+    pub call_timeout: u64,
+    /// Seconds after which all the attempts of a call time out. By default, only each attempt
+    /// times out
+    #[arg(
+        long,
+        global = true,
+        value_parser = clap::value_parser!(u64).range(1..),
+        env = ENV_RETRY_TIMEOUT
+    )]
+    pub retry_timeout: Option<u64>,
+}
+
+/// Flags for accessing IPFS.
+#[derive(Args)]
+pub struct IpfsArgs {
     /// IPFS endpoint. Format http(s)?://<host>:<port>
+    #[arg(long, short = 'i', env = ENV_IPFS_ENDPOINT)]
     pub ipfs_endpoint: Endpoint,
+}
 
-    // Positional arguments.
-    pub filepath: PathBuf,
-    /// The path to set for the uploaded file
-    #[arg(value_parser = validate_remote_path)]
-    pub remote_path: Option<String>,
+/// Flags for uploading directories.
+/// Besides them, the files that match the rules of the `.ipfsignore` files found in the directory
+/// tree are never uploaded.
+#[derive(Args)]
+pub struct DirArgs {
+    /// Wrap the directory into another directory when uploading a directory
+    #[arg(long)]
+    pub wrap: bool,
+    /// Glob of the files to upload when uploading a directory. It can be used several times
+    #[arg(long)]
+    pub include: Vec<String>,
+    /// Glob of the files to not upload when uploading a directory. It can be used several times
+    #[arg(long)]
+    pub exclude: Vec<String>,
+}
+
+/// Formats in which the commands can print their results.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    /// Human friendly format
+    Table,
+    /// JSON format
+    Json,
+}
+
+/// Parses the command-line arguments of the process. It exits the process when they are invalid
+/// or the help or version is requested.
+///
+/// The values of the profile of the configuration file are used as the default values of the
+/// flags, so the precedence of the values is flag > environment variable > profile.
+pub fn parse() -> Result<App, Error> {
+    parse_from(std::env::args_os().collect())
+}
+
+/// Parses `args` like [`parse`].
+pub(crate) fn parse_from(args: Vec<OsString>) -> Result<App, Error> {
+    let config_file = global_arg(&args, "config", None, Some(ENV_CONFIG)).map(PathBuf::from);
+    let profile =
+        global_arg(&args, "profile", None, Some(ENV_PROFILE)).and_then(|p| p.into_string().ok());
+
+    let config = config::Config::load(config_file.as_deref())?;
+    let profile = config.profile(profile.as_deref())?;
+
+    let values = profile.map(|(_, p)| p.values()).unwrap_or_default();
+    let (matches, values) = get_matches(args, values).unwrap_or_else(|err| err.exit());
+    let mut app = App::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    if let Command::Config(ConfigCommand::Show(show)) = &mut app.command {
+        show.config_file = config_file
+            .or_else(config::default_path)
+            .filter(|p| p.is_file());
+        show.profile = profile.map(|(n, _)| String::from(n));
+        if let Some(m) = matches
+            .subcommand_matches("config")
+            .and_then(|m| m.subcommand_matches("show"))
+        {
+            show.settings = settings(m, &values);
+        }
+    }
+
+    Ok(app)
+}
+
+/// The values of a profile indexed by the ID of their flags (see [`config::Profile::values`]).
+type ProfileValues = Vec<(&'static str, String)>;
+
+/// Parses `args` with the `values` of a profile, indexed by the ID of their flags, as the default
+/// values of the flags. It returns the matches and the values of the profile which are used.
+///
+/// The signer of the owner is chosen by its kind rather than by where it comes from (see
+/// `cmd::owner_signer`), so the owner's values of the profile aren't used when an owner's flag is
+/// passed through the command-line or an environment variable.
+fn get_matches(
+    args: Vec<OsString>,
+    mut values: ProfileValues,
+) -> Result<(ArgMatches, ProfileValues), clap::Error> {
+    let matches = command(&values).try_get_matches_from(args.clone())?;
+    if !passed_owner(&matches) || !values.iter().any(|(id, _)| is_owner_flag(id)) {
+        return Ok((matches, values));
+    }
+
+    values.retain(|(id, _)| !is_owner_flag(id));
+    let matches = command(&values).try_get_matches_from(args)?;
+    Ok((matches, values))
+}
+
+/// Returns the definition of the command-line with the `values` of a profile, indexed by the ID
+/// of their flags, as the default values of the flags.
+fn command(values: &[(&'static str, String)]) -> clap::Command {
+    let mut cmd = App::command();
+    for (id, value) in values {
+        let value = value.clone();
+        // The flag is no longer required because it has a value.
+        cmd = mut_arg(cmd, id, move |a| {
+            a.default_value(value.clone()).required(false)
+        });
+    }
+
+    if values.iter().any(|(id, _)| OWNER_KEY_SOURCES.contains(id)) {
+        cmd = mut_arg(cmd, "ether_owner_priv_key", optional_priv_key);
+    }
+
+    cmd
+}
+
+/// Returns the private key flag `arg` without the requirement of being present unless another
+/// owner's key is, because clap doesn't consider the default values of the other flags, which are
+/// the ones of the profile. The requirement cannot be removed, so the flag is defined again.
+fn optional_priv_key(arg: clap::Arg) -> clap::Arg {
+    let mut priv_key = clap::Arg::new(arg.get_id().clone())
+        .value_parser(arg.get_value_parser().clone())
+        .action(clap::ArgAction::Set)
+        .env(ENV_ETHER_OWNER_PRIV_KEY)
+        .hide_env_values(true)
+        .hide_default_value(true)
+        .conflicts_with_all(OWNER_KEY_SOURCES);
+    if let Some(h) = arg.get_help() {
+        priv_key = priv_key.help(h.clone());
+    }
+    if let Some(l) = arg.get_long() {
+        priv_key = priv_key.long(l.to_owned());
+    }
+    if let Some(s) = arg.get_short() {
+        priv_key = priv_key.short(s);
+    }
+    if let Some(n) = arg.get_value_names() {
+        priv_key = priv_key.value_names(n.to_vec());
+    }
+    if let [v] = arg.get_default_values() {
+        priv_key = priv_key.default_value(v.clone());
+    }
+
+    priv_key
+}
+
+/// Applies `f` to the flags with `id` of `cmd` and all its subcommands.
+fn mut_arg<F>(mut cmd: clap::Command, id: &'static str, f: F) -> clap::Command
+where
+    F: Fn(clap::Arg) -> clap::Arg + Clone,
+{
+    if cmd.get_arguments().any(|a| a.get_id() == id) {
+        cmd = cmd.mut_arg(id, f.clone());
+    }
+
+    let names: Vec<String> = cmd
+        .get_subcommands()
+        .map(|s| String::from(s.get_name()))
+        .collect();
+    for n in names {
+        let f = f.clone();
+        cmd = cmd.mut_subcommand(n, |s| mut_arg(s, id, f));
+    }
+
+    cmd
+}
+
+/// Returns whether a flag of [`OWNER_FLAGS`] is passed through the command-line to the subcommand
+/// of `matches` or through its environment variable.
+fn passed_owner(matches: &ArgMatches) -> bool {
+    let mut matches = matches;
+    while let Some((_, m)) = matches.subcommand() {
+        matches = m;
+    }
+
+    OWNER_FLAGS.iter().any(|(id, env)| {
+        (matches.ids().any(|m| m == id)
+            && matches.value_source(id) == Some(ValueSource::CommandLine))
+            || std::env::var_os(env).is_some_and(|v| !v.is_empty())
+    })
+}
+
+/// Returns whether `id` is the ID of a flag of [`OWNER_FLAGS`].
+fn is_owner_flag(id: &str) -> bool {
+    OWNER_FLAGS.iter().any(|(o, _)| *o == id)
+}
+
+/// Returns the value of the global flag with the long `name`, or the `short` name, from `args` or
+/// from the `env` environment variable if it isn't present.
+/// It's used for finding the configuration before parsing the command-line, which depends on it.
+fn global_arg(
+    args: &[OsString],
+    name: &str,
+    short: Option<char>,
+    env: Option<&str>,
+) -> Option<OsString> {
+    let flag = format!("--{}", name);
+    let flag_eq = format!("--{}=", name);
+    let short = short.map(|s| format!("-{}", s));
+
+    let mut iter = args.iter().skip(1);
+    while let Some(a) = iter.next() {
+        let a = match a.to_str() {
+            Some(a) => a,
+            None => continue,
+        };
+
+        if a == "--" {
+            break;
+        }
+        if a == flag || short.as_deref() == Some(a) {
+            return iter.next().cloned();
+        }
+        if let Some(v) = a.strip_prefix(&flag_eq) {
+            return Some(OsString::from(v));
+        }
+        if let Some(v) = short.as_deref().and_then(|s| a.strip_prefix(s)) {
+            return Some(OsString::from(v.strip_prefix('=').unwrap_or(v)));
+        }
+    }
+
+    env.and_then(std::env::var_os)
+}
+
+/// Returns the output format passed through the command-line, or the default one when it isn't
+/// passed or it's invalid. It's used for reporting the errors of [`parse`] in the requested
+/// format.
+pub fn output_format() -> OutputFormat {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    output_arg(&args)
+}
+
+/// Returns the output format of `args` like [`output_format`].
+pub(crate) fn output_arg(args: &[OsString]) -> OutputFormat {
+    global_arg(args, "output", Some('o'), None)
+        .and_then(|o| OutputFormat::from_str(&o.to_string_lossy(), false).ok())
+        .unwrap_or(OutputFormat::Table)
+}
+
+/// Returns the settings of the flags of all the subcommands which can be set by an environment
+/// variable, hence by a profile, except the ones which select the configuration. The flags with
+/// the same ID are only returned once.
+/// The value of each flag is resolved with the precedence of [`parse`], from the config show
+/// subcommand `matches`, which only has the global flags, the environment variables and
+/// `profile`.
+fn settings(matches: &ArgMatches, values: &[(&'static str, String)]) -> Vec<Setting> {
+    let mut cmd = App::command();
+    cmd.build();
+    let mut args = Vec::new();
+    configurable_args(&cmd, &mut args);
+
+    args.into_iter()
+        .map(|arg| {
+            let id = arg.get_id().as_str();
+            let join = |values: Vec<String>| Some(values.join(",")).filter(|v| !v.is_empty());
+
+            let (value, source) = if matches.ids().any(|m| m == id)
+                && matches.value_source(id) == Some(ValueSource::CommandLine)
+            {
+                let values = matches
+                    .get_raw(id)
+                    .map(|v| v.map(|v| v.to_string_lossy().into_owned()).collect())
+                    .unwrap_or_default();
+                (join(values), Some(SettingSource::Flag))
+            } else if let Some(v) = arg
+                .get_env()
+                .and_then(std::env::var_os)
+                .filter(|v| !v.is_empty())
+            {
+                (
+                    Some(v.to_string_lossy().into_owned()),
+                    Some(SettingSource::Env),
+                )
+            } else if let Some((_, v)) = values.iter().find(|(pid, _)| *pid == id) {
+                (Some(v.clone()), Some(SettingSource::Profile))
+            } else {
+                let values = arg
+                    .get_default_values()
+                    .iter()
+                    .map(|v| v.to_string_lossy().into_owned())
+                    .collect();
+                match join(values) {
+                    Some(v) => (Some(v), Some(SettingSource::Default)),
+                    None => (None, None),
+                }
+            };
+
+            Setting {
+                name: String::from(arg.get_long().unwrap_or(id)),
+                value,
+                source,
+                secret: arg.is_hide_env_values_set(),
+            }
+        })
+        .collect()
+}
+
+/// Appends to `args` the flags of the subcommands of `cmd` and of `cmd` itself which have an
+/// environment variable and aren't in `args` yet, except the ones which select the configuration.
+fn configurable_args(cmd: &clap::Command, args: &mut Vec<clap::Arg>) {
+    for s in cmd.get_subcommands() {
+        configurable_args(s, args);
+    }
+
+    for a in cmd.get_arguments() {
+        let id = a.get_id();
+        if a.get_env().is_some()
+            && id != "config"
+            && id != "profile"
+            && !args.iter().any(|b| b.get_id() == id)
+        {
+            args.push(a.clone());
+        }
+    }
 }
 
 /// Validates if a passed Ethereum address is of a valid format.
@@ -46,11 +1275,7 @@ fn validate_ether_address(addr: &str) -> Result<String, String> {
 fn validate_ether_private_key(key: &str) -> Result<String, String> {
     use ethers::signers::LocalWallet;
 
-    let key = if key.starts_with("0x") {
-        &key[2..]
-    } else {
-        key
-    };
+    let key = key.strip_prefix("0x").unwrap_or(key);
 
     match key.parse::<LocalWallet>() {
         Ok(_) => Ok(String::from(key)),
@@ -58,6 +1283,71 @@ fn validate_ether_private_key(key: &str) -> Result<String, String> {
     }
 }
 
+/// Validates if a passed BIP-32 derivation path is of a valid format.
+fn validate_derivation_path(path: &str) -> Result<String, String> {
+    use ethers::signers::{coins_bip39::English, MnemonicBuilder};
+
+    match MnemonicBuilder::<English>::default().derivation_path(path) {
+        Ok(_) => Ok(String::from(path)),
+        Err(err) => Err(format!("invalid format for derivation path. {}", err)),
+    }
+}
+
+/// Validates if a passed BIP-32 derivation path prefix, to which the account indices are appended,
+/// is of a valid format.
+fn validate_derivation_path_prefix(prefix: &str) -> Result<String, String> {
+    if !prefix.ends_with('/') {
+        return Err(String::from(
+            "invalid derivation path prefix, it MUST end with '/'",
+        ));
+    }
+
+    validate_derivation_path(&mnemonic::path_for_index(prefix, 0)).map(|_| String::from(prefix))
+}
+
+/// Validates if a passed amount of gwei is of a valid format.
+fn validate_gwei(amount: &str) -> Result<String, String> {
+    use ethers::utils::{parse_units, ParseUnits};
+
+    match parse_units(amount, "gwei") {
+        Ok(ParseUnits::U256(_)) => Ok(String::from(amount)),
+        Ok(ParseUnits::I256(_)) => Err(String::from("invalid amount of gwei, it's negative")),
+        Err(err) => Err(format!("invalid format for amount of gwei. {}", err)),
+    }
+}
+
+/// Validates if a passed gas limit multiplier is a number greater than or equal to 1.
+fn validate_gas_limit_multiplier(multiplier: &str) -> Result<f64, String> {
+    match multiplier.parse::<f64>() {
+        Ok(m) if m >= 1.0 && m.is_finite() => Ok(m),
+        Ok(_) => Err(String::from(
+            "invalid gas limit multiplier, it MUST be greater than or equal to 1",
+        )),
+        Err(err) => Err(format!("invalid format for gas limit multiplier. {}", err)),
+    }
+}
+
+/// Validates if a passed Ethereum transaction hash is of a valid format.
+fn validate_tx_hash(hash: &str) -> Result<String, String> {
+    use ethers::types::TxHash;
+
+    match hash.parse::<TxHash>() {
+        Ok(_) => Ok(String::from(hash)),
+        Err(err) => Err(format!(
+            "invalid format for Ethereum transaction hash. {}",
+            err
+        )),
+    }
+}
+
+/// Validates if a passed CID is of a valid format.
+fn validate_cid(cid: &str) -> Result<String, String> {
+    match cid::Cid::try_from(cid) {
+        Ok(_) => Ok(String::from(cid)),
+        Err(err) => Err(format!("invalid format for CID. {}", err)),
+    }
+}
+
 fn validate_remote_path(p: &str) -> Result<String, String> {
     if p.starts_with("/") {
         Ok(String::from(p))
@@ -125,5 +1415,974 @@ impl std::str::FromStr for Endpoint {
 
 #[cfg(test)]
 mod test {
-    // TODO: Write tests for the validate functions, Endpoint::from_str, and Endpoint::fmt.
+    // TODO: Write tests for Endpoint::from_str and Endpoint::fmt. The validate functions are only
+    // tested through the flags which use them.
+    use super::*;
+
+    #[test]
+    fn test_command_profile_defaults() {
+        let profile = config::Profile {
+            ether_chain_id: Some(1337),
+            ether_contract_address: Some(String::from(
+                "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            )),
+            ether_endpoint: Some(String::from("http://localhost:8545")),
+            ether_owner_priv_key: None,
+            keystore: None,
+            password_file: None,
+            mnemonic_file: None,
+            derivation_path: None,
+            ether_node_account: None,
+            remote_signer: None,
+            remote_signer_account: None,
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_label: None,
+            tx_type: Some(String::from("legacy")),
+            max_fee: None,
+            priority_fee: None,
+            gas_price: Some(1.5),
+            gas_limit_multiplier: None,
+            fee_cap: None,
+            pending_timeout: None,
+            fee_bump: None,
+            max_replacements: None,
+            max_attempts: None,
+            retry_backoff: None,
+            call_timeout: None,
+            retry_timeout: None,
+            confirmations: Some(3),
+            finalized: None,
+            ipfs_endpoint: Some(String::from("http://localhost:5001")),
+        };
+
+        let parse = |args: &[&str]| {
+            command(&profile.values())
+                .try_get_matches_from(args)
+                .and_then(|m| App::from_arg_matches(&m))
+        };
+
+        let app = parse(&[
+            "ipfs-cids-owners-cli",
+            "list",
+            "-e",
+            "http://ethereum:8545",
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        ])
+        .expect("required flags set by the profile");
+        match app.command {
+            Command::List(args) => {
+                assert_eq!(args.ether.ehter_chain_id, 1337, "chain ID from profile");
+                assert_eq!(
+                    args.ether.ether_contract_address, "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+                    "contract address from profile"
+                );
+                assert_eq!(
+                    args.ether.ether_endpoint.to_string(),
+                    "http://ethereum:8545",
+                    "flag overrides profile"
+                );
+            }
+            _ => panic!("expected list subcommand"),
+        }
+
+        let app = parse(&[
+            "ipfs-cids-owners-cli",
+            "pin",
+            "ls",
+            "--owner",
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        ])
+        .expect("owner filter requirements set by the profile");
+        match app.command {
+            Command::Pin(PinCommand::Ls(args)) => {
+                assert!(
+                    args.filter.ether_endpoint.is_some(),
+                    "endpoint from profile"
+                )
+            }
+            _ => panic!("expected pin ls subcommand"),
+        }
+
+        let app = parse(&[
+            "ipfs-cids-owners-cli",
+            "register",
+            "-p",
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u",
+        ])
+        .expect("gas options set by the profile");
+        match app.command {
+            Command::Register(args) => {
+                assert!(
+                    matches!(args.gas.tx_type, TxType::Legacy),
+                    "transaction type from profile"
+                );
+                assert_eq!(
+                    args.gas.gas_price.as_deref(),
+                    Some("1.5"),
+                    "gas price from profile"
+                );
+                assert_eq!(
+                    args.tx.confirmation.confirmations, 3,
+                    "confirmations from profile"
+                );
+            }
+            _ => panic!("expected register subcommand"),
+        }
+
+        assert!(
+            parse(&["ipfs-cids-owners-cli", "upload-and-register", "hello.txt"]).is_err(),
+            "private key isn't in the profile"
+        );
+    }
+
+    #[test]
+    fn test_get_matches_profile_owner() {
+        let profile = config::Profile {
+            ether_contract_address: Some(String::from(
+                "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            )),
+            ether_endpoint: Some(String::from("http://localhost:8545")),
+            keystore: Some(PathBuf::from("owner.json")),
+            password_file: Some(PathBuf::from("password.txt")),
+            ..Default::default()
+        };
+        let cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+        let parse = |args: &[&str]| {
+            get_matches(args.iter().map(OsString::from).collect(), profile.values())
+                .and_then(|(m, _)| App::from_arg_matches(&m))
+        };
+
+        let app = parse(&["ipfs-cids-owners-cli", "register", cid])
+            .expect("the owner's key is set by the profile");
+        match app.command {
+            Command::Register(args) => {
+                assert_eq!(
+                    args.owner.keystore.keystore,
+                    Some(PathBuf::from("owner.json")),
+                    "keystore from profile"
+                );
+                assert_eq!(
+                    args.owner.keystore.password_file,
+                    Some(PathBuf::from("password.txt")),
+                    "password file from profile"
+                );
+                assert_eq!(args.owner.ether_owner_priv_key, None, "no private key");
+            }
+            _ => panic!("expected register subcommand"),
+        }
+
+        let app = parse(&[
+            "ipfs-cids-owners-cli",
+            "register",
+            "--mnemonic-file",
+            "mnemonic.txt",
+            "--derivation-path",
+            "m/44'/60'/0'/0/1",
+            cid,
+        ])
+        .expect("the owner's key is passed");
+        match app.command {
+            Command::Register(args) => {
+                assert_eq!(
+                    args.owner.mnemonic.mnemonic_file,
+                    Some(PathBuf::from("mnemonic.txt")),
+                    "mnemonic from flag"
+                );
+                assert_eq!(
+                    args.owner.keystore.keystore, None,
+                    "the keystore of the profile is ignored"
+                );
+                assert_eq!(
+                    args.owner.keystore.password_file, None,
+                    "the password file of the profile is ignored"
+                );
+                assert_eq!(
+                    args.ether.ether_endpoint.to_string(),
+                    "http://localhost:8545",
+                    "the rest of the profile is used"
+                );
+            }
+            _ => panic!("expected register subcommand"),
+        }
+
+        let (_, values) = get_matches(
+            [
+                "ipfs-cids-owners-cli",
+                "register",
+                "-p",
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                cid,
+            ]
+            .iter()
+            .map(OsString::from)
+            .collect(),
+            profile.values(),
+        )
+        .expect("the private key is passed");
+        assert!(
+            values.iter().all(|(id, _)| !is_owner_flag(id)),
+            "the owner's values of the profile aren't used"
+        );
+    }
+
+    #[test]
+    fn test_global_arg() {
+        let args: Vec<OsString> = [
+            "ipfs-cids-owners-cli",
+            "--profile",
+            "local",
+            "list",
+            "--config=/tmp/config.toml",
+            "--",
+            "--profile",
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+
+        assert_eq!(
+            global_arg(&args, "profile", None, Some("IPFS_CIDS_OWNERS_TEST_UNSET")),
+            Some(OsString::from("local")),
+            "separated value"
+        );
+        assert_eq!(
+            global_arg(&args, "config", None, Some("IPFS_CIDS_OWNERS_TEST_UNSET")),
+            Some(OsString::from("/tmp/config.toml")),
+            "value with equal sign"
+        );
+        assert_eq!(
+            global_arg(&args[..3], "output", Some('o'), None),
+            None,
+            "not present"
+        );
+
+        let output = |args: &[&str]| {
+            let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            output_arg(&args)
+        };
+        assert!(
+            matches!(
+                output(&["ipfs-cids-owners-cli", "--output", "json", "list"]),
+                OutputFormat::Json
+            ),
+            "long output flag"
+        );
+        assert!(
+            matches!(
+                output(&["ipfs-cids-owners-cli", "list", "-ojson"]),
+                OutputFormat::Json
+            ),
+            "short output flag"
+        );
+        assert!(
+            matches!(
+                output(&["ipfs-cids-owners-cli", "-o", "xml", "list"]),
+                OutputFormat::Table
+            ),
+            "invalid output flag"
+        );
+    }
+
+    #[test]
+    fn test_app_list_doesnt_require_private_key() {
+        let app = App::try_parse_from([
+            "ipfs-cids-owners-cli",
+            "list",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+            "--limit",
+            "10",
+            "-o",
+            "json",
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        ])
+        .expect("valid list arguments");
+
+        assert!(
+            matches!(app.output, OutputFormat::Json),
+            "global output flag after the subcommand"
+        );
+        match app.command {
+            Command::List(args) => {
+                assert_eq!(args.offset, 0, "offset default value");
+                assert_eq!(args.limit, Some(10), "limit");
+                assert_eq!(
+                    args.owner_address, "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "owner address"
+                );
+            }
+            _ => panic!("expected list subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from([
+                "ipfs-cids-owners-cli",
+                "list",
+                "-a",
+                "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+                "-e",
+                "http://localhost:8545",
+                "not an address",
+            ])
+            .is_err(),
+            "owner address must be validated"
+        );
+    }
+
+    #[test]
+    fn test_app_upload_only_requires_ipfs_endpoint() {
+        let app = App::try_parse_from([
+            "ipfs-cids-owners-cli",
+            "upload",
+            "-i",
+            "http://localhost:5001",
+            "hello.txt",
+            "/hello.txt",
+        ])
+        .expect("valid upload arguments");
+
+        match app.command {
+            Command::Upload(args) => {
+                assert_eq!(args.filepath, PathBuf::from("hello.txt"), "filepath");
+                assert_eq!(
+                    args.remote_path.as_deref(),
+                    Some("/hello.txt"),
+                    "remote path"
+                );
+            }
+            _ => panic!("expected upload subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_app_pin_owner_filter() {
+        let app = App::try_parse_from([
+            "ipfs-cids-owners-cli",
+            "pin",
+            "ls",
+            "-i",
+            "http://localhost:5001",
+        ])
+        .expect("valid pin ls arguments without owner");
+        match app.command {
+            Command::Pin(PinCommand::Ls(args)) => assert!(args.filter.owner.is_none(), "owner"),
+            _ => panic!("expected pin ls subcommand"),
+        }
+
+        // The Ethereum flags required by the owner are checked when the command runs because
+        // they can be set by the profile.
+        let app = App::try_parse_from([
+            "ipfs-cids-owners-cli",
+            "pin",
+            "ls",
+            "-i",
+            "http://localhost:5001",
+            "--owner",
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        ])
+        .expect("valid pin ls arguments with owner");
+        match app.command {
+            Command::Pin(PinCommand::Ls(args)) => assert!(
+                args.filter.ether_contract_address.is_none(),
+                "owner without contract address"
+            ),
+            _ => panic!("expected pin ls subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from([
+                "ipfs-cids-owners-cli",
+                "pin",
+                "add",
+                "-i",
+                "http://localhost:5001"
+            ])
+            .is_err(),
+            "CIDs are required without owner"
+        );
+
+        let app = App::try_parse_from([
+            "ipfs-cids-owners-cli",
+            "pin",
+            "add",
+            "-i",
+            "http://localhost:5001",
+            "--owner",
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+        ])
+        .expect("valid pin add arguments with owner and without CIDs");
+        match app.command {
+            Command::Pin(PinCommand::Add(args)) => assert!(args.cids.is_empty(), "CIDs"),
+            _ => panic!("expected pin add subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_app_register_validates_cids() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "register",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+            "-p",
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        ];
+
+        let app = App::try_parse_from(args.iter().chain(&[
+            "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u",
+            "bafkreidgvpkjawlxz6sffxzwgooowe5yt7i6wsyg236mfoks77nywkptdq",
+        ]))
+        .expect("valid register arguments");
+
+        match app.command {
+            Command::Register(args) => assert_eq!(
+                args.cids,
+                vec![
+                    "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u",
+                    "bafkreidgvpkjawlxz6sffxzwgooowe5yt7i6wsyg236mfoks77nywkptdq"
+                ],
+                "CIDs"
+            ),
+            _ => panic!("expected register subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from(args.iter()).is_err(),
+            "at least one CID is required"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["fake CID"])).is_err(),
+            "CIDs must be validated"
+        );
+    }
+
+    #[test]
+    fn test_app_register_mnemonic() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "register",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+            "--mnemonic-file",
+            "mnemonic.txt",
+        ];
+        let cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+
+        let app = App::try_parse_from(args.iter().chain(&["--account-index", "3", cid]))
+            .expect("valid register arguments with mnemonic");
+        match app.command {
+            Command::Register(args) => {
+                assert_eq!(
+                    args.owner.mnemonic.mnemonic_file,
+                    Some(PathBuf::from("mnemonic.txt")),
+                    "mnemonic file"
+                );
+                assert_eq!(args.owner.mnemonic.account_index, Some(3), "account index");
+            }
+            _ => panic!("expected register subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from(
+                args.iter()
+                    .chain(&["--derivation-path", "m/44'/60'/0'/0/1", cid])
+            )
+            .is_ok(),
+            "derivation path"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--derivation-path", "m/invalid", cid]))
+                .is_err(),
+            "derivation path must be validated"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&[
+                "--derivation-path",
+                "m/44'/60'/0'/0/1",
+                "--account-index",
+                "1",
+                cid
+            ]))
+            .is_err(),
+            "derivation path conflicts with account index"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["-k", "keystore.json", cid])).is_err(),
+            "mnemonic conflicts with keystore"
+        );
+    }
+
+    #[test]
+    fn test_app_register_external_signer() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "register",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+        ];
+        let account = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+        let cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+
+        let app = App::try_parse_from(args.iter().chain(&["--ether-node-account", account, cid]))
+            .expect("valid register arguments with node account");
+        match app.command {
+            Command::Register(args) => assert_eq!(
+                args.owner.external_signer.ether_node_account.as_deref(),
+                Some(account),
+                "node account"
+            ),
+            _ => panic!("expected register subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from(args.iter().chain(&[
+                "--remote-signer",
+                "http://localhost:9000",
+                "--remote-signer-account",
+                account,
+                cid
+            ]))
+            .is_ok(),
+            "remote signer"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&[
+                "--remote-signer",
+                "http://localhost:9000",
+                cid
+            ]))
+            .is_err(),
+            "remote signer requires the account"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&[
+                "--ether-node-account",
+                account,
+                "-p",
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+                cid
+            ]))
+            .is_err(),
+            "node account conflicts with private key"
+        );
+    }
+
+    #[test]
+    fn test_app_register_dry_run() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "register",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+            "-p",
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            "--dry-run",
+        ];
+        let cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+
+        let app = App::try_parse_from(args.iter().chain(&[cid])).expect("valid dry run");
+        match app.command {
+            Command::Register(args) => assert!(args.dry_run, "dry run"),
+            _ => panic!("expected register subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--no-wait", cid])).is_err(),
+            "dry run conflicts with no wait"
+        );
+    }
+
+    #[test]
+    fn test_app_register_gas_options() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "register",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+            "-p",
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        ];
+        let cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+
+        let app = App::try_parse_from(args.iter().chain(&[
+            "--max-fee",
+            "30",
+            "--priority-fee",
+            "1.5",
+            "--gas-limit-multiplier",
+            "1.2",
+            "--fee-cap",
+            "50",
+            cid,
+        ]))
+        .expect("valid gas options");
+        match app.command {
+            Command::Register(args) => {
+                assert!(
+                    matches!(args.gas.tx_type, TxType::Eip1559),
+                    "type-2 transactions by default"
+                );
+                assert_eq!(args.gas.max_fee.as_deref(), Some("30"), "max fee");
+                assert_eq!(
+                    args.gas.priority_fee.as_deref(),
+                    Some("1.5"),
+                    "priority fee"
+                );
+                assert_eq!(args.gas.gas_limit_multiplier, 1.2, "gas limit multiplier");
+                assert_eq!(args.gas.fee_cap.as_deref(), Some("50"), "fee cap");
+            }
+            _ => panic!("expected register subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from(args.iter().chain(&[
+                "--tx-type",
+                "legacy",
+                "--gas-price",
+                "2",
+                cid
+            ]))
+            .is_ok(),
+            "legacy transaction with gas price"
+        );
+        assert!(
+            App::try_parse_from(
+                args.iter()
+                    .chain(&["--gas-price", "2", "--max-fee", "3", cid])
+            )
+            .is_err(),
+            "gas price conflicts with max fee"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--max-fee", "-1", cid])).is_err(),
+            "negative fee"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--max-fee", "abc", cid])).is_err(),
+            "invalid fee"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--gas-limit-multiplier", "0.5", cid]))
+                .is_err(),
+            "gas limit multiplier lower than 1"
+        );
+    }
+
+    #[test]
+    fn test_app_retry() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "pin",
+            "ls",
+            "-i",
+            "http://localhost:5001",
+        ];
+
+        let app = App::try_parse_from(args).expect("valid arguments");
+        assert_eq!(app.retry.max_attempts, 3, "default max attempts");
+        assert_eq!(app.retry.retry_backoff, 500, "default retry backoff");
+        assert_eq!(app.retry.call_timeout, 120, "default call timeout");
+        assert_eq!(app.retry.retry_timeout, None, "no retry timeout by default");
+
+        let app = App::try_parse_from(args.iter().chain(&[
+            "--max-attempts",
+            "5",
+            "--retry-backoff",
+            "100",
+            "--call-timeout",
+            "0",
+            "--retry-timeout",
+            "60",
+        ]))
+        .expect("valid retry flags after the subcommand");
+        assert_eq!(app.retry.max_attempts, 5, "max attempts");
+        assert_eq!(app.retry.retry_backoff, 100, "retry backoff");
+        assert_eq!(app.retry.call_timeout, 0, "call timeout");
+        assert_eq!(app.retry.retry_timeout, Some(60), "retry timeout");
+
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--max-attempts", "0"])).is_err(),
+            "zero attempts"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--retry-timeout", "0"])).is_err(),
+            "zero retry timeout"
+        );
+    }
+
+    #[test]
+    fn test_app_resume() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "resume",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+            "-i",
+            "http://localhost:5001",
+            "-p",
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        ];
+
+        let app = App::try_parse_from(args.iter().chain(&[
+            "--journal-dir",
+            "/tmp/journals",
+            "--no-wait",
+            "0123456789abcdef",
+        ]))
+        .expect("valid resume arguments");
+        match app.command {
+            Command::Resume(args) => {
+                assert_eq!(
+                    args.journal.journal_dir,
+                    Some(PathBuf::from("/tmp/journals")),
+                    "journals directory"
+                );
+                assert!(args.tx.no_wait, "no wait");
+                assert_eq!(args.id.as_deref(), Some("0123456789abcdef"), "run ID");
+            }
+            _ => panic!("expected resume subcommand"),
+        }
+
+        let app = App::try_parse_from(args).expect("valid arguments");
+        match app.command {
+            Command::Resume(args) => {
+                assert_eq!(args.journal.journal_dir, None, "default journals directory");
+                assert_eq!(args.id, None, "latest run by default");
+            }
+            _ => panic!("expected resume subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_app_tx_speedup() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "tx",
+            "speedup",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+            "-p",
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        ];
+        let hash = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060";
+
+        let app = App::try_parse_from(args.iter().chain(&[
+            "--pending-timeout",
+            "60",
+            "--fee-bump",
+            "15",
+            "--max-replacements",
+            "5",
+            hash,
+        ]))
+        .expect("valid replacement options");
+        match app.command {
+            Command::Tx(TxCommand::Speedup(args)) => {
+                assert_eq!(args.gas.pending_timeout, Some(60), "pending timeout");
+                assert_eq!(args.gas.fee_bump, 15, "fee bump");
+                assert_eq!(args.gas.max_replacements, 5, "max replacements");
+                assert_eq!(args.hash, hash, "hash");
+            }
+            _ => panic!("expected tx speedup subcommand"),
+        }
+
+        let app = App::try_parse_from(args.iter().chain(&[hash])).expect("valid arguments");
+        match app.command {
+            Command::Tx(TxCommand::Speedup(args)) => {
+                assert_eq!(
+                    args.gas.pending_timeout, None,
+                    "no pending timeout by default"
+                );
+                assert_eq!(args.gas.fee_bump, 20, "default fee bump");
+                assert_eq!(args.gas.max_replacements, 3, "default max replacements");
+            }
+            _ => panic!("expected tx speedup subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--fee-bump", "5", hash])).is_err(),
+            "fee bump lower than 10"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--pending-timeout", "0", hash])).is_err(),
+            "zero pending timeout"
+        );
+        assert!(
+            App::try_parse_from(args.iter().chain(&["0x01"])).is_err(),
+            "invalid hash"
+        );
+        assert!(
+            App::try_parse_from([
+                "ipfs-cids-owners-cli",
+                "tx",
+                "cancel",
+                "-a",
+                "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+                "-e",
+                "http://localhost:8545",
+                "--no-wait",
+                hash,
+            ])
+            .is_err(),
+            "cancel requires the owner"
+        );
+    }
+
+    #[test]
+    fn test_app_register_confirmations() {
+        let args = [
+            "ipfs-cids-owners-cli",
+            "register",
+            "-a",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "-e",
+            "http://localhost:8545",
+            "-p",
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        ];
+        let cid = "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u";
+
+        let app = App::try_parse_from(args.iter().chain(&[cid])).expect("default confirmations");
+        match app.command {
+            Command::Register(args) => {
+                assert_eq!(args.tx.confirmation.confirmations, 1, "one confirmation");
+                assert!(!args.tx.confirmation.finalized, "not finalized");
+            }
+            _ => panic!("expected register subcommand"),
+        }
+
+        let app =
+            App::try_parse_from(args.iter().chain(&["--finalized", cid])).expect("valid finalized");
+        match app.command {
+            Command::Register(args) => assert!(args.tx.confirmation.finalized, "finalized"),
+            _ => panic!("expected register subcommand"),
+        }
+
+        assert!(
+            App::try_parse_from(args.iter().chain(&["--confirmations", "0", cid])).is_err(),
+            "zero confirmations"
+        );
+        assert!(
+            App::try_parse_from(
+                args.iter()
+                    .chain(&["--confirmations", "3", "--finalized", cid])
+            )
+            .is_err(),
+            "confirmations conflicts with finalized"
+        );
+        assert!(
+            App::try_parse_from(
+                args.iter()
+                    .chain(&["--confirmations", "3", "--no-wait", cid])
+            )
+            .is_err(),
+            "confirmations conflicts with no wait"
+        );
+    }
+
+    #[test]
+    fn test_settings() {
+        let matches = App::command()
+            .try_get_matches_from([
+                "ipfs-cids-owners-cli",
+                "--max-attempts",
+                "5",
+                "config",
+                "show",
+            ])
+            .expect("valid config show");
+        let matches = matches
+            .subcommand_matches("config")
+            .and_then(|m| m.subcommand_matches("show"))
+            .expect("config show matches");
+        let profile = config::Profile {
+            fee_bump: Some(50),
+            ether_owner_priv_key: Some(String::from(
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            )),
+            ..Default::default()
+        };
+
+        let settings = settings(matches, &profile.values());
+        let setting = |name: &str| {
+            settings
+                .iter()
+                .find(|s| s.name == name)
+                .unwrap_or_else(|| panic!("setting {name}"))
+        };
+
+        assert_eq!(
+            settings.iter().filter(|s| s.name == "fee-bump").count(),
+            1,
+            "flags of several subcommands are shown once"
+        );
+        assert!(
+            settings
+                .iter()
+                .all(|s| s.name != "config" && s.name != "profile"),
+            "the flags selecting the configuration aren't shown"
+        );
+
+        let max_attempts = setting("max-attempts");
+        assert_eq!(max_attempts.value.as_deref(), Some("5"), "flag value");
+        assert_eq!(
+            max_attempts.source,
+            Some(SettingSource::Flag),
+            "flag source"
+        );
+
+        let fee_bump = setting("fee-bump");
+        assert_eq!(fee_bump.value.as_deref(), Some("50"), "profile value");
+        assert_eq!(
+            fee_bump.source,
+            Some(SettingSource::Profile),
+            "profile source"
+        );
+
+        let priv_key = setting("ether-owner-priv-key");
+        assert_eq!(
+            priv_key.source,
+            Some(SettingSource::Profile),
+            "private key source"
+        );
+        assert!(priv_key.secret, "the private key is secret");
+
+        let max_replacements = setting("max-replacements");
+        assert_eq!(
+            max_replacements.value.as_deref(),
+            Some("3"),
+            "default value"
+        );
+        assert_eq!(
+            max_replacements.source,
+            Some(SettingSource::Default),
+            "default source"
+        );
+
+        let max_fee = setting("max-fee");
+        assert_eq!(max_fee.value, None, "unset value");
+        assert_eq!(max_fee.source, None, "unset source");
+    }
 }
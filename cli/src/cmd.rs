@@ -1,74 +1,1209 @@
 //! Commands offered by the application.
 
 use crate::{
-    cli,
+    cli, config,
     error::{BoxError, Error},
-    ethereum, ipfs,
+    ethereum, grpc, ipfs, journal, keystore, lock, manifest, mnemonic, pkcs11, retry, server,
+    signer, watch,
 };
 
-use ethers::{abi::AbiEncode, core::types::Address, signers::LocalWallet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ethers::{
+    abi::AbiEncode,
+    core::types::{Address, TxHash, U256},
+    signers::{LocalWallet, Signer},
+};
+use futures::{
+    future,
+    stream::{self, StreamExt},
+};
 use ipfs_api_backend_hyper::{IpfsClient, TryFromUri};
+use serde::Serialize;
 
-/// Uploads a file specified by the command-line to IPFS and register it's CID to the CIDsOwners
-/// smart contract.
-pub async fn upload_and_register(args: cli::App) -> Result<UploadRegisterSummary, Error> {
-    let ipfs_cli = IpfsClient::from_host_and_port(
-        args.ipfs_endpoint.scheme,
-        &args.ipfs_endpoint.host,
-        args.ipfs_endpoint.port,
+/// Uploads a file or directory specified by the command-line to IPFS and register it's CID to the
+/// CIDsOwners smart contract.
+/// When it's a directory, the CIDs of its entries are also registered if the command-line
+/// specifies it.
+///
+/// The progress of the run is recorded in a journal, so it can be resumed with [`resume`] when it
+/// fails.
+pub async fn upload_and_register(
+    args: cli::UploadAndRegister,
+    retry: retry::RetryPolicy,
+) -> Result<UploadRegisterSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?
+        .with_gas_options(gas_options(&args.gas)?)
+        .with_finality(finality(&args.tx.confirmation));
+    let session = session(&cids_owners, &args.owner)?;
+
+    let filepath = args
+        .filepath
+        .canonicalize()
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => {
+                Error::invalid_arguments("filepath", "file or directory not found")
+            }
+            _ => Error::internal(
+                "system error when resolving the file path",
+                BoxError::from(err),
+            ),
+        })?;
+    let content_hash = journal::content_hash(
+        &filepath,
+        &dir_options(&args.dir, args.remote_path.as_deref()),
+    )?;
+
+    let mut journal = journal::Journal::create_in(
+        &journal_dir(&args.journal)?,
+        journal::Run {
+            filepath,
+            remote_path: args.remote_path,
+            wrap: args.dir.wrap,
+            include: args.dir.include,
+            exclude: args.dir.exclude,
+            register_children: args.register_children,
+            content_hash,
+            owner: session.address(),
+            contract_address: parse_ether_address(&args.ether.ether_contract_address)?,
+            chain_id: args.ether.ehter_chain_id,
+        },
+    )?;
+
+    run_journal(
+        &mut journal,
+        &ipfs::Client::with_client(&ipfs_cli, retry),
+        &session,
+        &args.tx,
     )
-    // TODO: verify if IpfsClient checks the connectivity and if it may return error because of
-    // other reasons, in that case this error could vary between `Error::InvalidArguments` and
-    // `Error::Internal`.
-    .map_err(|_| {
+    .await
+}
+
+/// Resumes the run of the upload-and-register subcommand specified by the command-line, or the
+/// latest one of the owner which isn't finished, skipping its finished steps.
+///
+/// The content is only uploaded when the run didn't upload it, and it fails if the content
+/// changed since the run started. The transactions which were already sent, even if their hashes
+/// weren't recorded, are waited instead of sending them again.
+pub async fn resume(
+    args: cli::Resume,
+    retry: retry::RetryPolicy,
+) -> Result<UploadRegisterSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?
+        .with_gas_options(gas_options(&args.gas)?)
+        .with_finality(finality(&args.tx.confirmation));
+    let session = session(&cids_owners, &args.owner)?;
+
+    let dir = journal_dir(&args.journal)?;
+    let mut journal = match &args.id {
+        Some(id) => journal::Journal::load_in(&dir, id)?,
+        None => journal::Journal::latest_in(&dir, session.address())?.ok_or_else(|| {
+            Error::invalid_arguments("id", "there isn't any run of the owner to resume")
+        })?,
+    };
+
+    let run = &journal.run;
+    if run.owner != session.address() {
+        return Err(Error::invalid_arguments(
+            "owner",
+            &format!(
+                "the run '{}' registers the CIDs to the owner {:?}",
+                journal.id, run.owner
+            ),
+        ));
+    }
+    if run.contract_address != parse_ether_address(&args.ether.ether_contract_address)?
+        || run.chain_id != args.ether.ehter_chain_id
+    {
+        return Err(Error::invalid_arguments(
+            "(ether_contract_address,ehter_chain_id)",
+            &format!(
+                "the run '{}' registers the CIDs to the contract {:?} of the chain {}",
+                journal.id, run.contract_address, run.chain_id
+            ),
+        ));
+    }
+
+    if journal.registrations.is_empty() {
+        let opts = journal_dir_options(run);
+        if journal::content_hash(&run.filepath, &opts)? != run.content_hash {
+            return Err(Error::invalid_arguments(
+                "id",
+                &format!(
+                    "the content of '{}' changed since the run '{}' started",
+                    run.filepath.display(),
+                    journal.id
+                ),
+            ));
+        }
+    }
+
+    run_journal(
+        &mut journal,
+        &ipfs::Client::with_client(&ipfs_cli, retry),
+        &session,
+        &args.tx,
+    )
+    .await
+}
+
+/// Uploads a file or directory specified by the command-line to IPFS without registering its CID.
+pub async fn upload(args: cli::Upload, retry: retry::RetryPolicy) -> Result<UploadSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+
+    if !args.filepath.is_dir() {
+        let uploaded = client
+            .uploload_file(&args.filepath, args.remote_path.as_deref())
+            .await?;
+
+        return Ok(UploadSummary {
+            cid: uploaded.cid,
+            size: uploaded.size,
+            entries: Vec::new(),
+        });
+    }
+
+    let uploaded = client
+        .upload_dir(
+            &args.filepath,
+            &dir_options(&args.dir, args.remote_path.as_deref()),
+        )
+        .await?;
+
+    Ok(UploadSummary {
+        cid: uploaded.root.cid,
+        size: uploaded.root.size,
+        entries: uploaded
+            .entries
+            .into_iter()
+            .map(|e| EntrySummary {
+                path: e.path,
+                cid: e.cid,
+                size: e.size,
+            })
+            .collect(),
+    })
+}
+
+/// Registers the CIDs specified by the command-line to the CIDsOwners smart contract without
+/// uploading anything to IPFS.
+///
+/// The transactions are sent back to back in the same order than the CIDs and it stops sending
+/// them at the first one that fails. It returns the error of the first registration that fails,
+/// however, the rest remain registered.
+pub async fn register(
+    args: cli::Register,
+    retry: retry::RetryPolicy,
+) -> Result<RegisterSummary, Error> {
+    let cids_owners = cids_owners(&args.ether, retry)?
+        .with_gas_options(gas_options(&args.gas)?)
+        .with_finality(finality(&args.tx.confirmation));
+    let session = session(&cids_owners, &args.owner)?;
+
+    let tx_hashes = register_cids(&session, &args.cids, &args.tx).await?;
+    let registrations = args
+        .cids
+        .into_iter()
+        .zip(tx_hashes)
+        .map(|(cid, ether_tx_hash)| UploadRegisterSummary {
+            cid,
+            ether_tx_hash,
+            pending: args.tx.no_wait,
+            journal: None,
+            children: Vec::new(),
+        })
+        .collect();
+
+    Ok(RegisterSummary { registrations })
+}
+
+/// Uploads the files listed in the manifest file specified by the command-line to IPFS and
+/// registers their CIDs to the CIDsOwners smart contract.
+///
+/// The files are uploaded concurrently and then their registration transactions are sent back to
+/// back. The result of each file is written to the result manifest file, so a file that fails
+/// doesn't prevent processing the rest.
+pub async fn batch(args: cli::Batch, retry: retry::RetryPolicy) -> Result<BatchSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?
+        .with_gas_options(gas_options(&args.gas)?)
+        .with_finality(finality(&args.confirmation));
+    let session = session(&cids_owners, &args.owner)?;
+
+    let result_path = match args.result {
+        Some(p) => p,
+        None => result_manifest_path(&args.manifest),
+    };
+    // Fail early if the result manifest format isn't supported.
+    manifest::Format::from_path(&result_path)?;
+
+    let rows = manifest::read(&args.manifest)?;
+    let base_dir = args.manifest.parent().unwrap_or_else(|| Path::new(""));
+
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+    let uploads: Vec<Result<ipfs::UploadedFile, Error>> = stream::iter(&rows)
+        .map(|row| {
+            let client = &client;
+            let filepath = base_dir.join(&row.filepath);
+            async move {
+                if filepath.is_dir() {
+                    return Err(Error::invalid_arguments(
+                        "filepath",
+                        "directories aren't supported in batch mode",
+                    ));
+                }
+
+                client
+                    .uploload_file(&filepath, row.remote_path.as_deref())
+                    .await
+            }
+        })
+        .buffered(args.concurrency as usize)
+        .collect()
+        .await;
+
+    let cids: Vec<String> = uploads
+        .iter()
+        .filter_map(|u| u.as_ref().ok().map(|u| u.cid.clone()))
+        .collect();
+    let mut registrations = session.register_cids(&cids).await.into_iter();
+
+    let mut summary = BatchSummary {
+        manifest: args.manifest.to_string_lossy().into_owned(),
+        result: result_path.to_string_lossy().into_owned(),
+        total: rows.len(),
+        registered: 0,
+        failed: 0,
+    };
+
+    let mut results = Vec::with_capacity(rows.len());
+    for (row, upload) in rows.into_iter().zip(uploads) {
+        let mut result = manifest::ResultRow {
+            filepath: row.filepath,
+            remote_path: row.remote_path,
+            cid: None,
+            ether_tx_hash: None,
+            error: None,
+        };
+
+        match upload {
+            Ok(uploaded) => {
+                result.cid = Some(uploaded.cid);
+                match registrations
+                    .next()
+                    .expect("BUG there must be one registration result for each uploaded file")
+                {
+                    Ok(receipt) => {
+                        result.ether_tx_hash = Some(receipt.transaction_hash.encode_hex())
+                    }
+                    Err(err) => result.error = Some(err.to_string()),
+                }
+            }
+            Err(err) => result.error = Some(err.to_string()),
+        }
+
+        if result.error.is_some() {
+            summary.failed += 1;
+        } else {
+            summary.registered += 1;
+        }
+
+        results.push(result);
+    }
+
+    manifest::write(&result_path, &results)?;
+    Ok(summary)
+}
+
+/// Simulates [`upload_and_register`] without storing anything in IPFS nor sending any transaction.
+/// The CIDs are computed by IPFS without storing the files and the registrations are simulated
+/// with `eth_call` for estimating their gas and cost.
+///
+/// Like [`upload_and_register`], it stops at the first registration that fails.
+pub async fn upload_and_register_dry_run(
+    args: cli::UploadAndRegister,
+    retry: retry::RetryPolicy,
+) -> Result<DryRunSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?.with_gas_options(gas_options(&args.gas)?);
+    let owner = owner_signer(&args.owner)?.address();
+
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+
+    let mut registrations = Vec::new();
+    if !args.filepath.is_dir() {
+        let cid = client.file_cid(&args.filepath).await?;
+        registrations.push(DryRunRegistration::new(None, cid));
+    } else {
+        let mut opts = dir_options(&args.dir, None);
+        opts.only_hash = true;
+        let hashed = client.upload_dir(&args.filepath, &opts).await?;
+
+        registrations.push(DryRunRegistration::new(None, hashed.root.cid));
+        if args.register_children {
+            for entry in hashed.entries {
+                registrations.push(DryRunRegistration::new(Some(entry.path), entry.cid));
+            }
+        }
+    }
+
+    dry_run(&cids_owners, owner, registrations, true).await
+}
+
+/// Simulates [`register`] without sending any transaction. The registrations are simulated with
+/// `eth_call` for estimating their gas and cost.
+///
+/// Like [`register`], it stops at the first registration that fails.
+pub async fn register_dry_run(
+    args: cli::Register,
+    retry: retry::RetryPolicy,
+) -> Result<DryRunSummary, Error> {
+    let cids_owners = cids_owners(&args.ether, retry)?.with_gas_options(gas_options(&args.gas)?);
+    let owner = owner_signer(&args.owner)?.address();
+
+    let registrations = args
+        .cids
+        .into_iter()
+        .map(|cid| DryRunRegistration::new(None, cid))
+        .collect();
+
+    dry_run(&cids_owners, owner, registrations, true).await
+}
+
+/// Simulates [`batch`] without storing anything in IPFS nor sending any transaction. The CIDs are
+/// computed by IPFS without storing the files and the registrations are simulated with `eth_call`
+/// for estimating their gas and cost. The result manifest isn't written.
+///
+/// Like [`batch`], the files that fail don't prevent processing the rest.
+pub async fn batch_dry_run(
+    args: cli::Batch,
+    retry: retry::RetryPolicy,
+) -> Result<DryRunSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?.with_gas_options(gas_options(&args.gas)?);
+    let owner = owner_signer(&args.owner)?.address();
+
+    let rows = manifest::read(&args.manifest)?;
+    let base_dir = args.manifest.parent().unwrap_or_else(|| Path::new(""));
+
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+    let registrations = stream::iter(rows)
+        .map(|row| {
+            let client = &client;
+            let filepath = base_dir.join(&row.filepath);
+            async move {
+                let cid = if filepath.is_dir() {
+                    Err(Error::invalid_arguments(
+                        "filepath",
+                        "directories aren't supported in batch mode",
+                    ))
+                } else {
+                    client.file_cid(&filepath).await
+                };
+
+                match cid {
+                    Ok(cid) => {
+                        DryRunRegistration::new(Some(row.filepath.display().to_string()), cid)
+                    }
+                    Err(err) => DryRunRegistration {
+                        path: Some(row.filepath.display().to_string()),
+                        cid: None,
+                        gas: None,
+                        error: Some(err.to_string()),
+                    },
+                }
+            }
+        })
+        .buffered(args.concurrency as usize)
+        .collect()
+        .await;
+
+    dry_run(&cids_owners, owner, registrations, false).await
+}
+
+/// Watches the directory specified by the command-line and uploads to IPFS and registers to the
+/// CIDsOwners smart contract the files which are added or changed. It only returns when there is
+/// an error which prevents it from continue watching.
+///
+/// The files that were added or changed while it wasn't watching are processed when it starts.
+/// A file is only uploaded and registered when its CID is different from the last one registered
+/// for its path, which is recorded in the state file. The result of each processed file is
+/// printed to the standard output in the `output` format, one line per file, and the files that
+/// fail are retried when they change again. The registrations which were sent without waiting
+/// until they were mined are checked when it starts, and the files whose transactions failed or
+/// were dropped are registered again.
+pub async fn watch(
+    args: cli::Watch,
+    retry: retry::RetryPolicy,
+    output: cli::OutputFormat,
+) -> Result<(), Error> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+    use std::collections::HashSet;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?
+        .with_gas_options(gas_options(&args.gas)?)
+        .with_finality(finality(&args.tx.confirmation));
+    let session = session(&cids_owners, &args.owner)?;
+
+    if !args.dir.is_dir() {
+        return Err(Error::invalid_arguments(
+            "dir",
+            "directory not found or it isn't a directory",
+        ));
+    }
+    let dir = args.dir.canonicalize().map_err(|err| {
+        Error::internal(
+            "system error when resolving the directory path",
+            BoxError::from(err),
+        )
+    })?;
+
+    let state_path = match &args.state {
+        // Absolute for identifying it among the paths reported by the watcher.
+        Some(p) if p.is_relative() => std::env::current_dir()
+            .map_err(|err| {
+                Error::internal(
+                    "system error when resolving the state file path",
+                    BoxError::from(err),
+                )
+            })?
+            .join(p),
+        Some(p) => p.clone(),
+        None => dir.join(watch::STATE_FILENAME),
+    };
+    let mut state = watch::State::load(&state_path)?;
+    recheck_pending(&cids_owners, &mut state).await?;
+    state.save(&state_path)?;
+    // The state file is written through a temporary file, see `watch::State::save`.
+    let state_tmp_path = state_path.with_extension("tmp");
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for p in event.paths {
+                    // The receiver is only dropped when the function returns.
+                    let _ = tx.send(p);
+                }
+            }
+        }
+    })
+    .map_err(|err| {
+        Error::internal(
+            "system error when creating the directory watcher",
+            BoxError::from(err),
+        )
+    })?;
+    watcher
+        .watch(&dir, RecursiveMode::Recursive)
+        .map_err(|err| {
+            Error::internal(
+                "system error when watching the directory",
+                BoxError::from(err),
+            )
+        })?;
+
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+    let settle = Duration::from_millis(args.settle_ms);
+    let mut changed: HashSet<PathBuf> = dir_files(&dir)?;
+    loop {
+        let mut paths: Vec<PathBuf> = changed.drain().collect();
+        paths.sort();
+        for path in paths {
+            if !path.is_file() || path == state_path || path == state_tmp_path {
+                continue;
+            }
+
+            let key = path
+                .strip_prefix(&dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .into_owned();
+
+            let res = watch_file(&client, &session, &args.tx, &path, state.get(&key)).await;
+
+            match res {
+                Ok(Some(entry)) => {
+                    match output {
+                        cli::OutputFormat::Table => println!(
+                            "File: '{}', CID: '{}', Etherem transaction hash: '{}'",
+                            key, entry.cid, entry.ether_tx_hash
+                        ),
+                        cli::OutputFormat::Json => println!(
+                            "{}",
+                            serde_json::json!({
+                                "file": key,
+                                "cid": entry.cid,
+                                "ether_tx_hash": entry.ether_tx_hash,
+                            })
+                        ),
+                    }
+                    state.insert(key, entry);
+                    state.save(&state_path)?;
+                }
+                Ok(None) => {}
+                Err(err) => match output {
+                    cli::OutputFormat::Table => println!("File: '{}', error: {}", key, err),
+                    cli::OutputFormat::Json => {
+                        println!("{}", serde_json::json!({ "file": key, "error": err }))
+                    }
+                },
+            }
+        }
+
+        match rx.recv().await {
+            Some(p) => changed.insert(p),
+            None => {
+                return Err(Error::internal(
+                    "directory watcher stopped unexpectedly",
+                    BoxError::from("watcher channel closed"),
+                ))
+            }
+        };
+
+        // Wait until the directory doesn't change during the settle time.
+        while let Ok(Some(p)) = tokio::time::timeout(settle, rx.recv()).await {
+            changed.insert(p);
+        }
+    }
+}
+
+/// Runs an HTTP server which exposes the upload and registration of files, the list of the CIDs
+/// registered by an owner and the status of the transactions through a REST API. It only returns
+/// if the server fails.
+pub async fn serve(args: cli::Serve, retry: retry::RetryPolicy) -> Result<(), Error> {
+    let cids_owners = cids_owners(&args.ether, retry)?
+        .with_gas_options(gas_options(&args.gas)?)
+        .with_finality(finality(&args.tx.confirmation));
+    let state = server::State {
+        ipfs: ipfs_client(&args.ipfs)?,
+        session: session(&cids_owners, &args.owner)?,
+        cids_owners,
+        retry,
+        tx: args.tx,
+        auth_token: args.auth_token,
+    };
+
+    server::run(state, args.listen, args.max_upload_size).await
+}
+
+/// Runs a gRPC server which exposes the upload and registration of files, the list of the CIDs
+/// registered by an owner and the status of the transactions. It only returns if the server fails.
+pub async fn serve_grpc(args: cli::ServeGrpc, retry: retry::RetryPolicy) -> Result<(), Error> {
+    let cids_owners = cids_owners(&args.ether, retry)?
+        .with_gas_options(gas_options(&args.gas)?)
+        .with_finality(finality(&args.confirmation));
+    let state = grpc::State {
+        ipfs: ipfs_client(&args.ipfs)?,
+        session: session(&cids_owners, &args.owner)?,
+        cids_owners,
+        retry,
+        auth_token: args.auth_token,
+    };
+
+    grpc::run(state, args.listen).await
+}
+
+/// Lists the CIDs registered by the owner specified by the command-line in the CIDsOwners smart
+/// contract.
+pub async fn list(args: cli::List, retry: retry::RetryPolicy) -> Result<ListSummary, Error> {
+    let cids_owners = cids_owners(&args.ether, retry)?;
+    let owner = parse_ether_address(&args.owner_address)?;
+
+    let cids = cids_owners.registered_cids(owner).await?;
+    let total = cids.len();
+
+    Ok(ListSummary {
+        owner: args.owner_address,
+        total,
+        offset: args.offset,
+        cids: paginate(cids, args.offset, args.limit),
+    })
+}
+
+/// Downloads the content of the CID specified by the command-line from IPFS to a file or the
+/// standard output.
+///
+/// Before downloading, it checks that the CID is registered by the specified owner in the
+/// CIDsOwners smart contract and it returns an error if it isn't, unless the command-line allows
+/// unregistered CIDs.
+pub async fn fetch(args: cli::Fetch, retry: retry::RetryPolicy) -> Result<FetchSummary, Error> {
+    use futures::io::AllowStdIo;
+
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?;
+    let owner = parse_ether_address(&args.owner)?;
+
+    // The same content can be registered with CIDs of different versions or bases.
+    let cids = cids_owners.registered_cids(owner).await?;
+    let registered = ipfs::multihash(&args.cid).is_some_and(|h| multihashes(&cids).contains(&h));
+    if !registered && !args.allow_unregistered {
+        return Err(Error::invalid_arguments(
+            "(cid,owner)",
+            "the CID isn't registered by the owner",
+        ));
+    }
+
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+    let size = match &args.out {
+        Some(out) => {
+            let mut file = async_fs::File::create(out).await.map_err(|err| {
+                Error::internal("system error when creating the file", BoxError::from(err))
+            })?;
+
+            let res = client.download(&args.cid, &mut file).await;
+            if res.is_err() {
+                // Don't leave partially downloaded files behind; it doesn't matter if it fails
+                // because the download error is more relevant.
+                let _ = async_fs::remove_file(out).await;
+            }
+            res?
+        }
+        None => {
+            client
+                .download(&args.cid, &mut AllowStdIo::new(std::io::stdout()))
+                .await?
+        }
+    };
+
+    Ok(FetchSummary {
+        cid: args.cid,
+        owner: args.owner,
+        registered,
+        out: args.out.map(|p| p.to_string_lossy().into_owned()),
+        size,
+    })
+}
+
+/// Pins the CIDs specified by the command-line in IPFS.
+/// When the command-line specifies an owner, only the CIDs registered by the owner are pinned and
+/// all of them when no CIDs are specified.
+pub async fn pin_add(args: cli::PinCids, retry: retry::RetryPolicy) -> Result<PinSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids = filter_owner_cids(&args.filter, retry, args.cids).await?;
+
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+    for cid in &cids {
+        client.pin(cid).await?;
+    }
+
+    Ok(PinSummary {
+        action: PinAction::Pinned,
+        cids,
+    })
+}
+
+/// Unpins the CIDs specified by the command-line from IPFS.
+/// When the command-line specifies an owner, only the CIDs registered by the owner are unpinned
+/// and all of them when no CIDs are specified.
+pub async fn pin_rm(args: cli::PinCids, retry: retry::RetryPolicy) -> Result<PinSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids = filter_owner_cids(&args.filter, retry, args.cids).await?;
+
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+    for cid in &cids {
+        client.unpin(cid).await?;
+    }
+
+    Ok(PinSummary {
+        action: PinAction::Unpinned,
+        cids,
+    })
+}
+
+/// Lists the CIDs pinned in IPFS.
+/// When the command-line specifies an owner, only the CIDs registered by the owner are listed.
+pub async fn pin_ls(args: cli::PinLs, retry: retry::RetryPolicy) -> Result<PinListSummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+
+    let mut pins = client.pins().await?;
+    if let Some(owner_cids) = owner_cids(&args.filter, retry).await? {
+        let owned = multihashes(&owner_cids);
+        pins.retain(|p| ipfs::multihash(&p.cid).is_some_and(|h| owned.contains(&h)));
+    }
+
+    Ok(PinListSummary {
+        pins: pins
+            .into_iter()
+            .map(|p| PinEntry {
+                cid: p.cid,
+                kind: p.kind,
+            })
+            .collect(),
+    })
+}
+
+/// Verifies that the CIDs registered by the owner specified by the command-line are pinned in
+/// IPFS and pins the ones that aren't if the command-line specifies it.
+pub async fn pin_verify(
+    args: cli::PinVerify,
+    retry: retry::RetryPolicy,
+) -> Result<PinVerifySummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?;
+    let owner = parse_ether_address(&args.owner_address)?;
+
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+    let pinned = multihashes(client.pins().await?.iter().map(|p| &p.cid));
+
+    let mut summary = PinVerifySummary {
+        owner: args.owner_address,
+        pinned: Vec::new(),
+        unpinned: Vec::new(),
+        invalid: Vec::new(),
+        repaired: args.repair,
+    };
+
+    let mut cids = cids_owners.registered_cids(owner).await?;
+    // The same CID can be registered several times.
+    cids.sort();
+    cids.dedup();
+    for cid in cids {
+        match ipfs::multihash(&cid) {
+            Some(h) if pinned.contains(&h) => summary.pinned.push(cid),
+            Some(_) => summary.unpinned.push(cid),
+            None => summary.invalid.push(cid),
+        }
+    }
+
+    if args.repair {
+        for cid in &summary.unpinned {
+            client.pin(cid).await?;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Shows the status of the Ethereum transaction specified by the command-line and the CID that
+/// it registers.
+pub async fn tx_status(
+    args: cli::TxStatus,
+    retry: retry::RetryPolicy,
+) -> Result<TxStatusSummary, Error> {
+    let cids_owners = cids_owners(&args.ether, retry)?;
+    let hash = args.hash.parse::<TxHash>().map_err(|err| {
+        Error::internal(
+            "BUG cli module should validate that the passed transaction hash is of a valid format",
+            BoxError::from(err),
+        )
+    })?;
+
+    let status = cids_owners.transaction_status(hash).await?;
+    Ok(TxStatusSummary::new(args.hash, status))
+}
+
+/// Replaces the pending Ethereum transaction specified by the command-line with the same one with
+/// higher fees.
+pub async fn tx_speedup(
+    args: cli::TxReplace,
+    retry: retry::RetryPolicy,
+) -> Result<TxReplaceSummary, Error> {
+    tx_replace(args, retry, ethereum::Replacement::SpeedUp).await
+}
+
+/// Replaces the pending Ethereum transaction specified by the command-line with a transfer of 0
+/// ether to the owner with higher fees, so the pending transaction isn't mined.
+pub async fn tx_cancel(
+    args: cli::TxReplace,
+    retry: retry::RetryPolicy,
+) -> Result<TxReplaceSummary, Error> {
+    tx_replace(args, retry, ethereum::Replacement::Cancel).await
+}
+
+/// Replaces the pending Ethereum transaction specified by the command-line with a transaction of
+/// `kind` and, unless the command-line specifies to not wait, waits until one of them is mined.
+async fn tx_replace(
+    args: cli::TxReplace,
+    retry: retry::RetryPolicy,
+    kind: ethereum::Replacement,
+) -> Result<TxReplaceSummary, Error> {
+    let cids_owners = cids_owners(&args.ether, retry)?
+        .with_gas_options(gas_options(&args.gas)?)
+        .with_finality(finality(&args.tx.confirmation));
+    let hash = args.hash.parse::<TxHash>().map_err(|err| {
+        Error::internal(
+            "BUG cli module should validate that the passed transaction hash is of a valid format",
+            BoxError::from(err),
+        )
+    })?;
+
+    let session = session(&cids_owners, &args.owner)?;
+    let replacement = session.replace(hash, kind).await?;
+
+    let mined = if args.tx.no_wait {
+        None
+    } else {
+        let receipt = session.wait_any(&[hash, replacement]).await?;
+        Some(receipt.transaction_hash.encode_hex())
+    };
+
+    Ok(TxReplaceSummary {
+        replaced: args.hash,
+        replacement: replacement.encode_hex(),
+        mined,
+    })
+}
+
+/// Shows the effective settings of the flags specified by the command-line, the environment
+/// variables and the profile of the configuration file. The private key is redacted.
+pub fn config_show(args: cli::ConfigShow) -> ConfigSummary {
+    ConfigSummary {
+        config_file: args.config_file.as_ref().map(|p| p.display().to_string()),
+        profile: args.profile,
+        settings: args
+            .settings
+            .into_iter()
+            .map(|s| SettingSummary {
+                name: s.name,
+                value: match s.value {
+                    Some(_) if s.secret => Some(String::from(REDACTED)),
+                    v => v,
+                },
+                source: s.source,
+            })
+            .collect(),
+    }
+}
+
+/// Returns the retry policy of the calls to IPFS and Ethereum from the global flags passed through
+/// the command-line.
+pub fn retry_policy(args: &cli::RetryArgs) -> retry::RetryPolicy {
+    retry::RetryPolicy {
+        max_attempts: args.max_attempts,
+        initial_backoff: Duration::from_millis(args.retry_backoff),
+        call_timeout: Some(Duration::from_secs(args.call_timeout)).filter(|t| !t.is_zero()),
+        timeout: args.retry_timeout.map(Duration::from_secs),
+    }
+}
+
+/// Creates a keystore with a new random private key encrypted with the password specified by the
+/// command-line.
+pub fn keys_new(args: cli::KeysNew) -> Result<KeySummary, Error> {
+    use ethers::core::{k256::ecdsa::SigningKey, rand::thread_rng};
+
+    let dir = keys_dir(&args.dir)?;
+    let password = keystore::read_password(args.password_file.as_deref(), true)?;
+    let key = SigningKey::random(&mut thread_rng());
+
+    let (path, wallet) = keystore::create(&dir, &key, &password, args.name.as_deref())?;
+    Ok(KeySummary {
+        path: path.display().to_string(),
+        address: format!("{:?}", wallet.address()),
+    })
+}
+
+/// Creates a keystore with the private key specified by the command-line encrypted with the
+/// password specified by the command-line.
+pub fn keys_import(args: cli::KeysImport) -> Result<KeySummary, Error> {
+    use ethers::core::k256::ecdsa::SigningKey;
+
+    let dir = keys_dir(&args.dir)?;
+    let priv_key = match &args.priv_key_file {
+        Some(p) => std::fs::read_to_string(p).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => {
+                Error::invalid_arguments("priv_key_file", "private key file not found")
+            }
+            _ => Error::internal(
+                "system error when reading the private key file",
+                BoxError::from(err),
+            ),
+        })?,
+        None => rpassword::prompt_password("Private key: ").map_err(|err| {
+            Error::internal(
+                "system error when prompting the private key",
+                BoxError::from(err),
+            )
+        })?,
+    };
+
+    let priv_key = priv_key.trim();
+    let bytes = ethers::utils::hex::decode(priv_key.strip_prefix("0x").unwrap_or(priv_key))
+        .map_err(|err| {
+            Error::invalid_arguments(
+                "priv_key",
+                &format!("invalid format for Ethereum private key. {}", err),
+            )
+        })?;
+    let key = SigningKey::from_bytes(&bytes).map_err(|err| {
         Error::invalid_arguments(
-            "ipfs-endpoint",
-            "endpoint doesn't correspond to an IPFS service",
+            "priv_key",
+            &format!("invalid Ethereum private key. {}", err),
         )
     })?;
 
-    let contract_addr = args.ether_contract_address
-            .parse::<Address>()
-            .map_err(|err|
-                     Error::internal(
-                         "BUG cli module should validate that the passed Etherem address is of a valid format",
-                         BoxError::from(err),
-                    ),
-            )?;
+    let password = keystore::read_password(args.password_file.as_deref(), true)?;
+    let (path, wallet) = keystore::create(&dir, &key, &password, args.name.as_deref())?;
+    Ok(KeySummary {
+        path: path.display().to_string(),
+        address: format!("{:?}", wallet.address()),
+    })
+}
 
-    let owner_wallet = args.ether_owner_priv_key
-            .parse::<LocalWallet>()
-            .map_err(|err|
-                     Error::internal(
-                         "BUG cli module should validate that the passed Etherem private key is of a valid format",
-                         BoxError::from(err),
-                    ),
-            )?;
+/// Lists the keystores of the directory specified by the command-line.
+pub fn keys_list(args: cli::KeysList) -> Result<KeyListSummary, Error> {
+    let dir = keys_dir(&args.dir)?;
+    let keys = keystore::list(&dir)?
+        .into_iter()
+        .map(|e| KeySummary {
+            path: e.path.display().to_string(),
+            address: e.address.unwrap_or_default(),
+        })
+        .collect();
 
-    let cids_owners = ethereum::CIDsOwners::new(
-        contract_addr,
-        &args.ether_endpoint.to_string(),
-        Some(args.ehter_chain_id),
-    )?;
+    Ok(KeyListSummary {
+        dir: dir.display().to_string(),
+        keys,
+    })
+}
 
-    let client = ipfs::Client::with_client(&ipfs_cli);
+/// Lists the addresses derived from the mnemonic specified by the command-line for its range of
+/// account indices.
+pub fn keys_derive(args: cli::KeysDerive) -> Result<DerivedKeyListSummary, Error> {
+    let phrase = mnemonic::read_phrase(&args.mnemonic_file)?;
+    let last = args.from.checked_add(args.count - 1).ok_or_else(|| {
+        Error::invalid_arguments("count", "the range of account indices overflows")
+    })?;
 
-    let cid = client
-        .uploload_file(&args.filepath, args.remote_path.as_deref())
-        .await?;
+    let mut keys = Vec::with_capacity(args.count as usize);
+    for index in args.from..=last {
+        let path = mnemonic::path_for_index(&args.path_prefix, index);
+        let wallet = mnemonic::derive(&phrase, &path)?;
+        keys.push(DerivedKeySummary {
+            index,
+            path,
+            address: format!("{:?}", wallet.address()),
+        });
+    }
+
+    Ok(DerivedKeyListSummary { keys })
+}
 
-    let receipt = cids_owners.register_cid_owner(&cid, owner_wallet).await?;
+/// Verifies if the CID of the file specified by the command-line is registered by the specified
+/// owner in the CIDsOwners smart contract.
+/// The CID is computed by IPFS without storing the file.
+pub async fn verify(args: cli::Verify, retry: retry::RetryPolicy) -> Result<VerifySummary, Error> {
+    let ipfs_cli = ipfs_client(&args.ipfs)?;
+    let cids_owners = cids_owners(&args.ether, retry)?;
+    let owner = parse_ether_address(&args.owner_address)?;
 
-    Ok(UploadRegisterSummary {
+    let client = ipfs::Client::with_client(&ipfs_cli, retry);
+    let cid = client.file_cid(&args.filepath).await?;
+
+    let cids = cids_owners.registered_cids(owner).await?;
+    let registered = ipfs::multihash(&cid).is_some_and(|h| multihashes(&cids).contains(&h));
+
+    Ok(VerifySummary {
+        filepath: args.filepath.to_string_lossy().into_owned(),
         cid,
-        ether_tx_hash: receipt.transaction_hash.encode_hex(),
+        owner: args.owner_address,
+        registered,
     })
 }
 
+/// Value shown instead of the secrets.
+const REDACTED: &str = "<redacted>";
+
+/// Contains the effective settings of the flags which can be configured.
+#[derive(Serialize)]
+pub struct ConfigSummary {
+    /// The loaded configuration file, if any.
+    pub config_file: Option<String>,
+    /// The applied profile, if any.
+    pub profile: Option<String>,
+    pub settings: Vec<SettingSummary>,
+}
+
+/// The effective value of a flag and where it comes from. Both are `None` when it isn't set.
+#[derive(Serialize)]
+pub struct SettingSummary {
+    pub name: String,
+    pub value: Option<String>,
+    pub source: Option<cli::SettingSource>,
+}
+
+impl std::fmt::Display for ConfigSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Config file: '{}', profile: '{}'",
+            self.config_file.as_deref().unwrap_or("none"),
+            self.profile.as_deref().unwrap_or("none")
+        )?;
+        writeln!(f)?;
+        write!(f, "{:<24}  {:<8}  Value", "Setting", "Source")?;
+        for s in &self.settings {
+            write!(
+                f,
+                "\n{:<24}  {:<8}  {}",
+                s.name,
+                s.source.map(|s| s.to_string()).unwrap_or_default(),
+                s.value.as_deref().unwrap_or("")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains the location and the address of a keystore.
+#[derive(Serialize)]
+pub struct KeySummary {
+    pub path: String,
+    /// The address of the key. Empty when it's unknown without decrypting the keystore.
+    pub address: String,
+}
+
+impl std::fmt::Display for KeySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Keystore: '{}', address: '{}'", self.path, self.address)
+    }
+}
+
+/// Contains the keystores of a directory.
+#[derive(Serialize)]
+pub struct KeyListSummary {
+    pub dir: String,
+    pub keys: Vec<KeySummary>,
+}
+
+impl std::fmt::Display for KeyListSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Directory: '{}', keystores: {}",
+            self.dir,
+            self.keys.len()
+        )?;
+        for k in &self.keys {
+            write!(f, "\n  {}  {}", k.address, k.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains the addresses derived from a mnemonic.
+#[derive(Serialize)]
+pub struct DerivedKeyListSummary {
+    pub keys: Vec<DerivedKeySummary>,
+}
+
+/// Contains the address derived from a mnemonic for an account index.
+#[derive(Serialize)]
+pub struct DerivedKeySummary {
+    pub index: u32,
+    pub path: String,
+    pub address: String,
+}
+
+impl std::fmt::Display for DerivedKeyListSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Derived addresses: {}", self.keys.len())?;
+        for k in &self.keys {
+            write!(f, "\n  {}  {}  {}", k.index, k.address, k.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains the simulated registrations of a dry run and their estimated gas and cost.
+#[derive(Serialize)]
+pub struct DryRunSummary {
+    /// The address that would send the transactions.
+    pub owner: String,
+    pub registrations: Vec<DryRunRegistration>,
+    /// The number of registrations that would fail.
+    pub failed: usize,
+    pub total_gas: u64,
+    /// The fee per gas of the transactions with the gas options, which is the maximum one for
+    /// type-2 transactions.
+    pub fee_per_gas_wei: String,
+    /// The cost of the total gas with the fee per gas.
+    pub estimated_cost_wei: String,
+    pub estimated_cost_eth: String,
+}
+
+/// Contains a simulated registration.
+#[derive(Serialize)]
+pub struct DryRunRegistration {
+    /// The path of the file, when the CID isn't passed directly.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The CID; `None` when it cannot be computed.
+    pub cid: Option<String>,
+    /// The gas that the transaction would use; `None` when the registration would fail.
+    pub gas: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DryRunRegistration {
+    fn new(path: Option<String>, cid: String) -> Self {
+        Self {
+            path,
+            cid: Some(cid),
+            gas: None,
+            error: None,
+        }
+    }
+}
+
+impl std::fmt::Display for DryRunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Dry run for the owner '{}', nothing was stored in IPFS nor sent to Ethereum",
+            self.owner
+        )?;
+        for r in &self.registrations {
+            write!(f, "\n  {}", r.cid.as_deref().unwrap_or("-"))?;
+            if let Some(gas) = r.gas {
+                write!(f, "  gas: {}", gas)?;
+            }
+            if let Some(p) = &r.path {
+                write!(f, "  '{}'", p)?;
+            }
+            if let Some(err) = &r.error {
+                write!(f, "  error: {}", err)?;
+            }
+        }
+
+        write!(
+            f,
+            "\nTotal gas: {}, fee per gas: {} wei, estimated cost: {} ETH",
+            self.total_gas, self.fee_per_gas_wei, self.estimated_cost_eth
+        )?;
+        if self.failed > 0 {
+            write!(f, ", registrations that would fail: {}", self.failed)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Contains information of successful file upload and CID registration.
+#[derive(Serialize)]
 pub struct UploadRegisterSummary {
     pub cid: String,
     pub ether_tx_hash: String,
+    /// Indicates that the transactions were sent without waiting until they were mined.
+    pub pending: bool,
+    /// The ID of the journal of the run, for resuming it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal: Option<String>,
+    /// The registrations of the entries of an uploaded directory.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ChildRegisterSummary>,
 }
 
 impl std::fmt::Display for UploadRegisterSummary {
@@ -77,11 +1212,1130 @@ impl std::fmt::Display for UploadRegisterSummary {
             f,
             "CID: '{}', Etherem transaction hash: '{}'",
             self.cid, self.ether_tx_hash
+        )?;
+        if self.pending {
+            write!(f, " (pending)")?;
+        }
+        if let Some(id) = &self.journal {
+            write!(f, ", run: '{}'", id)?;
+        }
+        for c in &self.children {
+            write!(
+                f,
+                "\n  Path: '{}', CID: '{}', Etherem transaction hash: '{}'",
+                c.path, c.cid, c.ether_tx_hash
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains information of a successful registration of an entry of an uploaded directory.
+#[derive(Serialize)]
+pub struct ChildRegisterSummary {
+    pub path: String,
+    pub cid: String,
+    pub ether_tx_hash: String,
+}
+
+/// Contains information of a successful file or directory upload.
+#[derive(Serialize)]
+pub struct UploadSummary {
+    pub cid: String,
+    /// The size in bytes reported by IPFS.
+    pub size: u64,
+    /// The entries of an uploaded directory.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<EntrySummary>,
+}
+
+impl std::fmt::Display for UploadSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CID: '{}', size: {} bytes", self.cid, self.size)?;
+        for e in &self.entries {
+            write!(
+                f,
+                "\n  Path: '{}', CID: '{}', size: {} bytes",
+                e.path, e.cid, e.size
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains information of an entry of an uploaded directory.
+#[derive(Serialize)]
+pub struct EntrySummary {
+    pub path: String,
+    pub cid: String,
+    pub size: u64,
+}
+
+/// Contains information of successful CIDs registrations.
+#[derive(Serialize)]
+pub struct RegisterSummary {
+    pub registrations: Vec<UploadRegisterSummary>,
+}
+
+impl std::fmt::Display for RegisterSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, r) in self.registrations.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", r)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains information of a processed manifest file in batch mode.
+#[derive(Serialize)]
+pub struct BatchSummary {
+    pub manifest: String,
+    /// The path of the result manifest file.
+    pub result: String,
+    pub total: usize,
+    pub registered: usize,
+    pub failed: usize,
+}
+
+impl std::fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Manifest: '{}', files: {}, registered: {}, failed: {}, results: '{}'",
+            self.manifest, self.total, self.registered, self.failed, self.result
+        )
+    }
+}
+
+/// Contains a page of the CIDs registered by an owner.
+#[derive(Serialize)]
+pub struct ListSummary {
+    pub owner: String,
+    /// The total number of CIDs registered by the owner, regardless of the page.
+    pub total: usize,
+    /// The position in the whole list of the first CID of the page.
+    pub offset: usize,
+    pub cids: Vec<String>,
+}
+
+impl std::fmt::Display for ListSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Owner: '{}', total registered CIDs: {}",
+            self.owner, self.total
+        )?;
+        writeln!(f)?;
+        write!(f, "{:>6}  CID", "#")?;
+        for (i, cid) in self.cids.iter().enumerate() {
+            write!(f, "\n{:>6}  {}", self.offset + i, cid)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains information of a successful CID download.
+#[derive(Serialize)]
+pub struct FetchSummary {
+    pub cid: String,
+    pub owner: String,
+    pub registered: bool,
+    /// The file where the content was written. `None` when it was written to the standard output.
+    pub out: Option<String>,
+    /// The number of downloaded bytes.
+    pub size: u64,
+}
+
+impl std::fmt::Display for FetchSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.registered {
+            writeln!(
+                f,
+                "WARNING: the CID isn't registered by the owner '{}'",
+                self.owner
+            )?;
+        }
+
+        write!(
+            f,
+            "CID: '{}', downloaded {} bytes to '{}'",
+            self.cid,
+            self.size,
+            self.out.as_deref().unwrap_or("standard output")
+        )
+    }
+}
+
+/// Contains the CIDs which were pinned or unpinned.
+#[derive(Serialize)]
+pub struct PinSummary {
+    pub action: PinAction,
+    pub cids: Vec<String>,
+}
+
+/// The action applied to the CIDs of a [`PinSummary`].
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinAction {
+    Pinned,
+    Unpinned,
+}
+
+impl std::fmt::Display for PinSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.action {
+            PinAction::Pinned => "Pinned",
+            PinAction::Unpinned => "Unpinned",
+        };
+
+        write!(f, "{} CIDs: {}", action, self.cids.len())?;
+        for cid in &self.cids {
+            write!(f, "\n  {}", cid)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains the CIDs pinned in IPFS.
+#[derive(Serialize)]
+pub struct PinListSummary {
+    pub pins: Vec<PinEntry>,
+}
+
+/// Contains a CID pinned in IPFS.
+#[derive(Serialize)]
+pub struct PinEntry {
+    pub cid: String,
+    /// The type of pin (e.g. recursive, direct).
+    pub kind: String,
+}
+
+impl std::fmt::Display for PinListSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<10} CID", "Type")?;
+        for p in &self.pins {
+            write!(f, "\n{:<10} {}", p.kind, p.cid)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains the result of verifying that the CIDs registered by an owner are pinned.
+#[derive(Serialize)]
+pub struct PinVerifySummary {
+    pub owner: String,
+    pub pinned: Vec<String>,
+    pub unpinned: Vec<String>,
+    /// Registered values which aren't valid CIDs.
+    pub invalid: Vec<String>,
+    /// Indicates if the unpinned CIDs were pinned.
+    pub repaired: bool,
+}
+
+impl std::fmt::Display for PinVerifySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Owner: '{}', pinned CIDs: {}, unpinned CIDs: {}, invalid CIDs: {}",
+            self.owner,
+            self.pinned.len(),
+            self.unpinned.len(),
+            self.invalid.len()
+        )?;
+
+        if !self.unpinned.is_empty() {
+            let state = if self.repaired {
+                "pinned now"
+            } else {
+                "not pinned"
+            };
+            for cid in &self.unpinned {
+                write!(f, "\n  {} ({})", cid, state)?;
+            }
+        }
+
+        for cid in &self.invalid {
+            write!(f, "\n  '{}' (invalid)", cid)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains the status of a transaction.
+#[derive(Serialize)]
+pub struct TxStatusSummary {
+    pub hash: String,
+    pub state: TxStateSummary,
+    pub from: String,
+    pub nonce: u64,
+    pub block_number: Option<u64>,
+    pub confirmations: u64,
+    /// The CID registered by the transaction. `None` when the transaction isn't a call to the
+    /// register method of the CIDsOwners smart contract.
+    pub cid: Option<String>,
+}
+
+impl TxStatusSummary {
+    /// Creates a summary of the `status` of the transaction with `hash`.
+    pub(crate) fn new(hash: String, status: ethereum::TxStatus) -> Self {
+        Self {
+            hash,
+            state: match status.state {
+                ethereum::TxState::Pending => TxStateSummary::Pending,
+                ethereum::TxState::Mined => TxStateSummary::Mined,
+                ethereum::TxState::Failed => TxStateSummary::Failed,
+            },
+            from: format!("{:?}", status.from),
+            nonce: status.nonce,
+            block_number: status.block_number,
+            confirmations: status.confirmations,
+            cid: status.cid,
+        }
+    }
+}
+
+/// The state of the transaction of a [`TxStatusSummary`].
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStateSummary {
+    Pending,
+    Mined,
+    Failed,
+}
+
+impl std::fmt::Display for TxStatusSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self.state {
+            TxStateSummary::Pending => "pending",
+            TxStateSummary::Mined => "mined",
+            TxStateSummary::Failed => "failed",
+        };
+
+        write!(
+            f,
+            "Transaction: '{}', state: {}, from: '{}', nonce: {}",
+            self.hash, state, self.from, self.nonce
+        )?;
+        if let Some(block) = self.block_number {
+            write!(
+                f,
+                ", block: {}, confirmations: {}",
+                block, self.confirmations
+            )?;
+        }
+
+        match &self.cid {
+            Some(cid) => write!(f, "\nRegistered CID: '{}'", cid),
+            None => write!(
+                f,
+                "\nIt isn't a call to the register method of the CIDsOwners smart contract"
+            ),
+        }
+    }
+}
+
+/// Contains the result of replacing a pending transaction.
+#[derive(Serialize)]
+pub struct TxReplaceSummary {
+    /// The hash of the replaced transaction.
+    pub replaced: String,
+    /// The hash of the replacement transaction.
+    pub replacement: String,
+    /// The hash of the mined transaction, which is the replaced one when it was mined before the
+    /// replacement. `None` when it didn't wait until one of them was mined.
+    pub mined: Option<String>,
+}
+
+impl std::fmt::Display for TxReplaceSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Transaction '{}' replaced by '{}'",
+            self.replaced, self.replacement
+        )?;
+        match &self.mined {
+            Some(hash) => write!(f, ", mined transaction: '{}'", hash),
+            None => write!(f, " (pending)"),
+        }
+    }
+}
+
+/// Contains the result of verifying if a file is registered by an owner.
+#[derive(Serialize)]
+pub struct VerifySummary {
+    pub filepath: String,
+    pub cid: String,
+    pub owner: String,
+    pub registered: bool,
+}
+
+impl std::fmt::Display for VerifySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verb = if self.registered { "is" } else { "isn't" };
+        write!(
+            f,
+            "File: '{}', CID: '{}' {} registered by the owner '{}'",
+            self.filepath, self.cid, verb, self.owner
         )
     }
 }
 
+/// Uploads and registers the file of `path` if its CID isn't the one of `registered`.
+/// It returns the new registration or `None` if the file didn't change.
+async fn watch_file(
+    client: &ipfs::Client<'_>,
+    session: &ethereum::Session,
+    args: &cli::TxArgs,
+    path: &Path,
+    registered: Option<&watch::Entry>,
+) -> Result<Option<watch::Entry>, Error> {
+    if let Some(r) = registered {
+        if client.file_cid(path).await? == r.cid {
+            return Ok(None);
+        }
+    }
+
+    let cid = client.uploload_file(path, None).await?.cid;
+    let ether_tx_hash = register_cid(session, &cid, args).await?;
+
+    Ok(Some(watch::Entry {
+        cid,
+        ether_tx_hash,
+        pending: args.no_wait,
+    }))
+}
+
+/// Checks the transactions of the pending registrations of `state`, which were sent without
+/// waiting until they were mined. The ones which are mined stop being pending and the ones which
+/// failed or were dropped are removed, so their files are registered again.
+async fn recheck_pending(
+    cids_owners: &ethereum::CIDsOwners,
+    state: &mut watch::State,
+) -> Result<(), Error> {
+    for (path, mut entry) in state.pending() {
+        let hash = entry.ether_tx_hash.parse::<TxHash>().map_err(|err| {
+            Error::invalid_arguments(
+                "state",
+                &format!(
+                    "invalid transaction hash '{}' of the file '{}' in the watch state file. {}",
+                    entry.ether_tx_hash, path, err
+                ),
+            )
+        })?;
+
+        match cids_owners.transaction_status(hash).await {
+            Ok(status) if status.state != ethereum::TxState::Failed => {
+                entry.pending = status.state == ethereum::TxState::Pending;
+                state.insert(path, entry);
+            }
+            // The transaction failed or it isn't found.
+            Ok(_) | Err(Error::InvalidArguments(_)) => {
+                state.remove(&path);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the paths of all the files of the `dir` directory tree.
+fn dir_files(dir: &Path) -> Result<std::collections::HashSet<PathBuf>, Error> {
+    let mut files = std::collections::HashSet::new();
+    for entry in ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .build()
+    {
+        let entry = entry.map_err(|err| {
+            Error::internal(
+                "system error when reading the directory tree",
+                BoxError::from(err),
+            )
+        })?;
+
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            files.insert(entry.into_path());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Simulates the registration to `owner` of the CID of each registration of `registrations`
+/// without error and sets their gas. When `fail_fast` is true, it returns the error of the first
+/// registration that fails, otherwise it sets the error to the registration.
+async fn dry_run(
+    cids_owners: &ethereum::CIDsOwners,
+    owner: Address,
+    mut registrations: Vec<DryRunRegistration>,
+    fail_fast: bool,
+) -> Result<DryRunSummary, Error> {
+    cids_owners.check_contract().await?;
+    let fee_per_gas = cids_owners.fee_per_gas().await?;
+
+    let mut total_gas = U256::zero();
+    for r in registrations.iter_mut() {
+        let cid = match (&r.cid, &r.error) {
+            (Some(cid), None) => cid,
+            _ => continue,
+        };
+
+        match cids_owners.estimate_register_cid_owner(cid, owner).await {
+            Ok(gas) => {
+                total_gas += gas;
+                r.gas = Some(gas.as_u64());
+            }
+            Err(err) if !fail_fast => r.error = Some(err.to_string()),
+            Err(err) => return Err(err),
+        }
+    }
+
+    let cost = total_gas * fee_per_gas;
+    let cost_eth = ethers::utils::format_units(cost, "ether").map_err(|err| {
+        Error::internal(
+            "BUG the estimated cost should always be convertible to ether",
+            BoxError::from(err),
+        )
+    })?;
+
+    Ok(DryRunSummary {
+        owner: format!("{:?}", owner),
+        failed: registrations.iter().filter(|r| r.error.is_some()).count(),
+        registrations,
+        total_gas: total_gas.as_u64(),
+        fee_per_gas_wei: fee_per_gas.to_string(),
+        estimated_cost_wei: cost.to_string(),
+        estimated_cost_eth: cost_eth,
+    })
+}
+
+/// Registers `cid` through `session` and returns the hash of the transaction. When `args`
+/// specifies to not wait, it returns as soon as the transaction is sent.
+pub(crate) async fn register_cid(
+    session: &ethereum::Session,
+    cid: &str,
+    args: &cli::TxArgs,
+) -> Result<String, Error> {
+    if args.no_wait {
+        let hash = session.send_register(cid).await?;
+        return Ok(hash.encode_hex());
+    }
+
+    let receipt = session.register(cid).await?;
+    Ok(receipt.transaction_hash.encode_hex())
+}
+
+/// Registers the CIDs of `cids` through `session` and returns the hashes of the transactions in
+/// the same order. The hash of a transaction which is replaced is the one of its mined
+/// replacement.
+///
+/// The transactions are sent back to back. When one cannot be sent, the rest aren't sent and it
+/// returns its error, whose message lists the hashes of the ones already sent because they may
+/// still be mined. When `args` specifies to not wait, it returns as soon as the
+/// transactions are sent; otherwise, it waits until all of them are mined and returns the error
+/// of the first one that fails.
+async fn register_cids(
+    session: &ethereum::Session,
+    cids: &[String],
+    args: &cli::TxArgs,
+) -> Result<Vec<String>, Error> {
+    let mut hashes = Vec::with_capacity(cids.len());
+    for cid in cids {
+        match session.send_register(cid).await {
+            Ok(h) => hashes.push(h),
+            Err(err) if hashes.is_empty() => return Err(err),
+            Err(err) => {
+                let sent: Vec<String> = cids
+                    .iter()
+                    .zip(&hashes)
+                    .map(|(cid, hash)| format!("{} (CID '{}')", hash.encode_hex(), cid))
+                    .collect();
+                return Err(err.with_note(&format!(
+                    "The transactions already sent, which may still be mined, are: {}",
+                    sent.join(", ")
+                )));
+            }
+        }
+    }
+
+    if !args.no_wait {
+        // The mined transactions may be replacements of the sent ones.
+        let receipts = future::join_all(hashes.iter().map(|h| session.wait(*h))).await;
+        hashes = receipts
+            .into_iter()
+            .map(|r| r.map(|r| r.transaction_hash))
+            .collect::<Result<_, _>>()?;
+    }
+
+    Ok(hashes.into_iter().map(|h| h.encode_hex()).collect())
+}
+
+/// Runs the steps of the run of `journal` which aren't finished and records them in the journal.
+/// The content is uploaded when it isn't yet. The registrations which aren't mined are sent back
+/// to back, except the ones whose transactions were already sent, and then it waits until all of
+/// them are mined, unless `args` specifies to not wait. It returns the error of the first
+/// registration that fails, like [`register_cids`].
+async fn run_journal(
+    journal: &mut journal::Journal,
+    client: &ipfs::Client<'_>,
+    session: &ethereum::Session,
+    args: &cli::TxArgs,
+) -> Result<UploadRegisterSummary, Error> {
+    if journal.registrations.is_empty() {
+        let run = &journal.run;
+
+        journal.registrations = if !run.filepath.is_dir() {
+            let cid = client
+                .uploload_file(&run.filepath, run.remote_path.as_deref())
+                .await?
+                .cid;
+            vec![journal::Registration::new(None, cid)]
+        } else {
+            let uploaded = client
+                .upload_dir(&run.filepath, &journal_dir_options(run))
+                .await?;
+
+            let mut registrations = vec![journal::Registration::new(None, uploaded.root.cid)];
+            if run.register_children {
+                registrations.extend(
+                    uploaded
+                        .entries
+                        .into_iter()
+                        .map(|e| journal::Registration::new(Some(e.path), e.cid)),
+                );
+            }
+            registrations
+        };
+        journal.save()?;
+    }
+
+    // The registrations which aren't mined and the hashes of their transactions.
+    let mut sent = Vec::new();
+    for i in 0..journal.registrations.len() {
+        let registration = &journal.registrations[i];
+        if registration.mined {
+            continue;
+        }
+
+        // The transaction may have been sent when its nonce is recorded.
+        let found = match (registration.nonce, registration.block) {
+            (Some(nonce), Some(block)) => {
+                session
+                    .find_register(&registration.cid, nonce, block, registration.tx_hash)
+                    .await?
+            }
+            _ => None,
+        };
+
+        let hash = match found {
+            Some(h) => h,
+            None => {
+                let cid = registration.cid.clone();
+                session
+                    .send_register_with_nonce(&cid, |nonce, block| {
+                        journal.registrations[i].nonce = Some(nonce);
+                        journal.registrations[i].block = Some(block);
+                        journal.save()
+                    })
+                    .await?
+            }
+        };
+
+        journal.registrations[i].tx_hash = Some(hash);
+        journal.save()?;
+        sent.push((i, hash));
+    }
+
+    if !args.no_wait {
+        // The mined transactions may be replacements of the sent ones.
+        let receipts = future::join_all(sent.iter().map(|(_, h)| session.wait(*h))).await;
+
+        let mut first_err = None;
+        for ((i, _), receipt) in sent.into_iter().zip(receipts) {
+            match receipt {
+                Ok(r) => {
+                    let registration = &mut journal.registrations[i];
+                    registration.tx_hash = Some(r.transaction_hash);
+                    registration.mined = true;
+                }
+                Err(err) => first_err = first_err.or(Some(err)),
+            }
+        }
+
+        journal.save()?;
+        if let Some(err) = first_err {
+            return Err(err);
+        }
+    }
+
+    let tx_hash = |r: &journal::Registration| {
+        r.tx_hash
+            .expect("BUG the transactions of all the registrations must be sent")
+            .encode_hex()
+    };
+    let mut registrations = journal.registrations.iter();
+    let root = registrations
+        .next()
+        .expect("BUG there must be a registration for the root CID");
+
+    Ok(UploadRegisterSummary {
+        cid: root.cid.clone(),
+        ether_tx_hash: tx_hash(root),
+        pending: !journal.is_finished(),
+        journal: Some(journal.id.clone()),
+        children: registrations
+            .map(|r| ChildRegisterSummary {
+                path: r.path.clone().unwrap_or_default(),
+                cid: r.cid.clone(),
+                ether_tx_hash: tx_hash(r),
+            })
+            .collect(),
+    })
+}
+
+/// Starts the registration session of the owner passed through the command-line, acquiring its
+/// lock.
+fn session(
+    cids_owners: &ethereum::CIDsOwners,
+    args: &cli::OwnerArgs,
+) -> Result<ethereum::Session, Error> {
+    let owner = owner_signer(args)?;
+    let lock = lock::OwnerLock::acquire(owner.address())?;
+    Ok(cids_owners.session(owner, lock))
+}
+
+/// Creates an IPFS client for the endpoint passed through the command-line.
+fn ipfs_client(args: &cli::IpfsArgs) -> Result<IpfsClient, Error> {
+    IpfsClient::from_host_and_port(
+        args.ipfs_endpoint.scheme.clone(),
+        &args.ipfs_endpoint.host,
+        args.ipfs_endpoint.port,
+    )
+    // TODO: verify if IpfsClient checks the connectivity and if it may return error because of
+    // other reasons, in that case this error could vary between `Error::InvalidArguments` and
+    // `Error::Internal`.
+    .map_err(|_| {
+        Error::invalid_arguments(
+            "ipfs-endpoint",
+            "endpoint doesn't correspond to an IPFS service",
+        )
+    })
+}
+
+/// Creates the options for uploading a directory from the flags passed through the command-line.
+fn dir_options<'a>(args: &'a cli::DirArgs, remote_path: Option<&'a str>) -> ipfs::DirOptions<'a> {
+    ipfs::DirOptions {
+        wrap: args.wrap,
+        remote_path,
+        include: &args.include,
+        exclude: &args.exclude,
+        only_hash: false,
+    }
+}
+
+/// Returns the options for uploading the directory of the run `run`.
+fn journal_dir_options(run: &journal::Run) -> ipfs::DirOptions<'_> {
+    ipfs::DirOptions {
+        wrap: run.wrap,
+        remote_path: run.remote_path.as_deref(),
+        include: &run.include,
+        exclude: &run.exclude,
+        only_hash: false,
+    }
+}
+
+/// Creates a CIDsOwners instance for the contract and endpoint passed through the command-line.
+fn cids_owners(
+    args: &cli::EthereumArgs,
+    retry: retry::RetryPolicy,
+) -> Result<ethereum::CIDsOwners, Error> {
+    new_cids_owners(
+        &args.ether_contract_address,
+        &args.ether_endpoint,
+        args.ehter_chain_id,
+        retry,
+    )
+}
+
+/// Creates the gas options of the transactions from the flags passed through the command-line.
+fn gas_options(args: &cli::GasArgs) -> Result<ethereum::GasOptions, Error> {
+    let gwei = |amount: &Option<String>| amount.as_deref().map(parse_gwei).transpose();
+
+    let tx_type = match args.tx_type {
+        cli::TxType::Legacy => ethereum::TxType::Legacy,
+        cli::TxType::Eip1559 => ethereum::TxType::Eip1559,
+    };
+
+    match tx_type {
+        ethereum::TxType::Legacy if args.max_fee.is_some() || args.priority_fee.is_some() => {
+            return Err(Error::invalid_arguments(
+                "max-fee",
+                "only type-2 transactions have maximum fees, use the gas price",
+            ))
+        }
+        ethereum::TxType::Eip1559 if args.gas_price.is_some() => {
+            return Err(Error::invalid_arguments(
+                "gas-price",
+                "only legacy transactions have a gas price, use the maximum fees",
+            ))
+        }
+        _ => {}
+    }
+
+    Ok(ethereum::GasOptions {
+        tx_type,
+        max_fee_per_gas: gwei(&args.max_fee)?,
+        max_priority_fee_per_gas: gwei(&args.priority_fee)?,
+        gas_price: gwei(&args.gas_price)?,
+        gas_limit_multiplier: args.gas_limit_multiplier,
+        fee_cap: gwei(&args.fee_cap)?,
+        pending_timeout: args.pending_timeout.map(Duration::from_secs),
+        fee_bump_percent: args.fee_bump,
+        max_replacements: args.max_replacements,
+    })
+}
+
+/// Returns when a mined transaction is considered final from the flags passed through the
+/// command-line.
+fn finality(args: &cli::ConfirmationArgs) -> ethereum::Finality {
+    if args.finalized {
+        ethereum::Finality::Finalized
+    } else {
+        ethereum::Finality::Confirmations(args.confirmations)
+    }
+}
+
+/// Creates a CIDsOwners instance for the passed contract address, endpoint and chain ID.
+fn new_cids_owners(
+    contract_address: &str,
+    endpoint: &cli::Endpoint,
+    chain_id: u64,
+    retry: retry::RetryPolicy,
+) -> Result<ethereum::CIDsOwners, Error> {
+    let contract_addr = parse_ether_address(contract_address)?;
+    ethereum::CIDsOwners::new(contract_addr, &endpoint.to_string(), Some(chain_id), retry)
+}
+
+/// Returns the CIDs registered by the owner passed through the command-line or `None` if the
+/// command-line doesn't filter by owner.
+async fn owner_cids(
+    args: &cli::OwnerFilterArgs,
+    retry: retry::RetryPolicy,
+) -> Result<Option<Vec<String>>, Error> {
+    let owner = if let Some(o) = &args.owner {
+        parse_ether_address(o)?
+    } else {
+        return Ok(None);
+    };
+
+    let (contract_address, endpoint) = match (&args.ether_contract_address, &args.ether_endpoint) {
+        (Some(a), Some(e)) => (a, e),
+        _ => {
+            return Err(Error::invalid_arguments(
+                "(ether-contract-address,ether-endpoint)",
+                "they are required for filtering by owner",
+            ))
+        }
+    };
+
+    let cids_owners = new_cids_owners(contract_address, endpoint, args.ehter_chain_id, retry)?;
+    cids_owners.registered_cids(owner).await.map(Some)
+}
+
+/// Returns the CIDs of `cids` which are registered by the owner passed through the command-line.
+/// When `cids` is empty, it returns all the valid CIDs registered by the owner. When the
+/// command-line doesn't filter by owner, it returns `cids`.
+async fn filter_owner_cids(
+    args: &cli::OwnerFilterArgs,
+    retry: retry::RetryPolicy,
+    cids: Vec<String>,
+) -> Result<Vec<String>, Error> {
+    let mut owner_cids = match owner_cids(args, retry).await? {
+        Some(c) => c,
+        None => return Ok(cids),
+    };
+
+    if cids.is_empty() {
+        // The same CID can be registered several times and the contract doesn't validate them.
+        owner_cids.retain(|c| ipfs::multihash(c).is_some());
+        owner_cids.sort();
+        owner_cids.dedup();
+        return Ok(owner_cids);
+    }
+
+    let owned = multihashes(&owner_cids);
+    Ok(cids
+        .into_iter()
+        .filter(|c| ipfs::multihash(c).is_some_and(|h| owned.contains(&h)))
+        .collect())
+}
+
+/// Returns the multihashes of the valid CIDs of `cids`.
+fn multihashes<I, S>(cids: I) -> std::collections::HashSet<Vec<u8>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    cids.into_iter()
+        .filter_map(|c| ipfs::multihash(c.as_ref()))
+        .collect()
+}
+
+/// Creates the signer of the owner's transactions specified by the command-line.
+/// When several signers are set, which only happens when some of them come from a configuration
+/// profile or an environment variable, the precedence is: remote signer > Ethereum node account >
+/// PKCS#11 token > keystore > mnemonic > private key.
+fn owner_signer(args: &cli::OwnerArgs) -> Result<ethereum::Owner, Error> {
+    let signer = &args.external_signer;
+    if let (Some(endpoint), Some(account)) = (&signer.remote_signer, &signer.remote_signer_account)
+    {
+        let account = parse_ether_address(account)?;
+        return Ok(ethereum::Owner::Remote(signer::RemoteSigner::new(
+            &endpoint.to_string(),
+            account,
+        )));
+    }
+
+    if let Some(account) = &signer.ether_node_account {
+        return Ok(ethereum::Owner::Node(parse_ether_address(account)?));
+    }
+
+    let token = &args.pkcs11;
+    if let (Some(module), Some(slot), Some(label)) =
+        (&token.pkcs11_module, token.pkcs11_slot, &token.pkcs11_label)
+    {
+        let pin = pkcs11::read_pin(token.pkcs11_pin_file.as_deref())?;
+        return Ok(ethereum::Owner::Pkcs11(pkcs11::Pkcs11Signer::new(
+            module, slot, label, &pin,
+        )?));
+    }
+
+    owner_wallet(args).map(ethereum::Owner::Wallet)
+}
+
+/// Creates the wallet of the owner from the private key passed through the command-line.
+/// The keystore and the mnemonic take precedence over the private key when they are set with it,
+/// which only happens when one of them comes from a configuration profile or an environment
+/// variable.
+fn owner_wallet(args: &cli::OwnerArgs) -> Result<LocalWallet, Error> {
+    if let Some(path) = &args.keystore.keystore {
+        let password = keystore::read_password(args.keystore.password_file.as_deref(), false)?;
+        return keystore::decrypt(&keystore::resolve(path), &password);
+    }
+
+    if let Some(file) = &args.mnemonic.mnemonic_file {
+        let phrase = mnemonic::read_phrase(file)?;
+        let path = match &args.mnemonic.derivation_path {
+            Some(p) => p.clone(),
+            None => mnemonic::path_for_index(
+                mnemonic::DEFAULT_PATH_PREFIX,
+                args.mnemonic.account_index.unwrap_or_default(),
+            ),
+        };
+        return mnemonic::derive(&phrase, &path);
+    }
+
+    match &args.ether_owner_priv_key {
+        Some(key) => key.parse::<LocalWallet>().map_err(|err| {
+            Error::internal(
+                "BUG cli module should validate that the passed Etherem private key is of a valid format",
+                BoxError::from(err),
+            )
+        }),
+        None => Err(Error::internal(
+            "BUG cli module should require the private key when no other owner's key is set",
+            BoxError::from("missing owner's key"),
+        )),
+    }
+}
+
+/// Returns the keystores directory specified by the command-line or the default one.
+fn keys_dir(args: &cli::KeysDirArgs) -> Result<PathBuf, Error> {
+    match &args.keys_dir {
+        Some(d) => Ok(d.clone()),
+        None => config::app_dir(keystore::KEYS_DIR).ok_or_else(|| {
+            Error::invalid_arguments(
+                "keys_dir",
+                "the default keystores directory cannot be found, set it explicitly",
+            )
+        }),
+    }
+}
+
+/// Returns the journals directory passed through the command-line or the default one.
+fn journal_dir(args: &cli::JournalArgs) -> Result<PathBuf, Error> {
+    match &args.journal_dir {
+        Some(d) => Ok(d.clone()),
+        None => config::app_dir(journal::JOURNALS_DIR).ok_or_else(|| {
+            Error::invalid_arguments(
+                "journal_dir",
+                "the default journals directory cannot be found, set it explicitly",
+            )
+        }),
+    }
+}
+
+/// Parses an amount of gwei passed through the command-line and returns it in wei.
+fn parse_gwei(amount: &str) -> Result<U256, Error> {
+    ethers::utils::parse_units(amount, "gwei")
+        .map(U256::from)
+        .map_err(|err| {
+            Error::internal(
+                "BUG cli module should validate that the passed amount of gwei is of a valid format",
+                BoxError::from(err),
+            )
+        })
+}
+
+/// Parses an Ethereum address passed through the command-line.
+fn parse_ether_address(addr: &str) -> Result<Address, Error> {
+    addr.parse::<Address>().map_err(|err| {
+        Error::internal(
+            "BUG cli module should validate that the passed Etherem address is of a valid format",
+            BoxError::from(err),
+        )
+    })
+}
+
+/// Returns the default path of the result manifest file of `manifest`, which is the same path with
+/// the `-result` suffix in the file name.
+fn result_manifest_path(manifest: &Path) -> PathBuf {
+    let stem = manifest
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut name = format!("{}-result", stem);
+    if let Some(ext) = manifest.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+
+    manifest.with_file_name(name)
+}
+
+/// Returns the page of `items` which starts at `offset` and contains at most `limit` items.
+/// When `limit` is `None`, the page contains all the items after `offset`.
+pub(crate) fn paginate<T>(items: Vec<T>, offset: usize, limit: Option<usize>) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
-    // TODO: write tests for the `upload_and_register` function.
+    // TODO: `upload_and_register` is only tested through the gRPC service and `resume` isn't
+    // tested; write tests for them with a local IPFS node and Ethereum node.
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        let items = vec![0, 1, 2, 3, 4];
+
+        assert_eq!(paginate(items.clone(), 0, None), items, "no pagination");
+        assert_eq!(
+            paginate(items.clone(), 2, None),
+            vec![2, 3, 4],
+            "only offset"
+        );
+        assert_eq!(
+            paginate(items.clone(), 0, Some(2)),
+            vec![0, 1],
+            "only limit"
+        );
+        assert_eq!(
+            paginate(items.clone(), 3, Some(5)),
+            vec![3, 4],
+            "limit beyond"
+        );
+        assert!(paginate(items, 5, Some(1)).is_empty(), "offset beyond");
+    }
+
+    #[test]
+    fn test_result_manifest_path() {
+        assert_eq!(
+            result_manifest_path(Path::new("/data/run-1.csv")),
+            PathBuf::from("/data/run-1-result.csv"),
+        );
+        assert_eq!(
+            result_manifest_path(Path::new("manifest.json")),
+            PathBuf::from("manifest-result.json"),
+        );
+    }
+
+    #[test_with::http(localhost:8545)]
+    #[test_with::env(IPFS_CIDS_OWNERS_CONTRACT_ADDRESS, IPFS_CIDS_OWNER_PRIV_KEY)]
+    #[tokio::test]
+    async fn test_register_dry_run() {
+        use clap::Parser;
+        use std::env;
+
+        let contract_addr = env::var("IPFS_CIDS_OWNERS_CONTRACT_ADDRESS").expect(
+            "BUG this test should be ignored without the 'IPFS_CIDS_OWNERS_CONTRACT_ADDRESS' env var",
+        );
+        let owner_priv_key = env::var("IPFS_CIDS_OWNER_PRIV_KEY").expect(
+            "BUG this test should be ignored without the 'IPFS_CIDS_OWNER_PRIV_KEY' env var",
+        );
+
+        let app = cli::App::try_parse_from([
+            "ipfs-cids-owners-cli",
+            "register",
+            "--dry-run",
+            "-c",
+            "1337",
+            "-a",
+            &contract_addr,
+            "-e",
+            "http://localhost:8545",
+            "-p",
+            &owner_priv_key,
+            "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u",
+        ])
+        .expect("valid register arguments");
+        let args = match app.command {
+            cli::Command::Register(args) => args,
+            _ => panic!("expected register subcommand"),
+        };
+
+        let cids_owners =
+            cids_owners(&args.ether, retry::RetryPolicy::default()).expect("create CIDsOwners");
+        let owner = owner_signer(&args.owner).expect("owner").address();
+        let registered = cids_owners
+            .registered_cids(owner)
+            .await
+            .expect("get registered CIDs")
+            .len();
+
+        let summary = register_dry_run(args, retry::RetryPolicy::default())
+            .await
+            .expect("dry run");
+        assert_eq!(summary.failed, 0, "failed registrations");
+        assert!(summary.total_gas > 0, "estimated gas");
+        assert_eq!(
+            summary.registrations[0].gas,
+            Some(summary.total_gas),
+            "registration gas"
+        );
+
+        assert_eq!(
+            cids_owners
+                .registered_cids(owner)
+                .await
+                .expect("get registered CIDs")
+                .len(),
+            registered,
+            "nothing is registered"
+        );
+    }
 }
@@ -0,0 +1,383 @@
+//! Configuration file which contains named profiles with the default values of the flags for
+//! accessing the IPFS and Ethereum networks (e.g. `local`, `sepolia`, `mainnet`).
+//!
+//! The file is in TOML format and each profile is a table under `profiles`:
+//!
+//! ```toml
+//! default_profile = "local"
+//!
+//! [profiles.local]
+//! ether_chain_id = 1337
+//! ether_contract_address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+//! ether_endpoint = "http://localhost:8545"
+//! ipfs_endpoint = "http://localhost:5001"
+//! ```
+//!
+//! The values of the profile are only used for the flags which aren't passed through the
+//! command-line nor through their environment variables.
+
+use crate::error::{BoxError, Error};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind as ioErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Name of the directory of the application in the user's configuration directory.
+const APP_DIR: &str = "ipfs-cids-owners";
+
+/// Name of the configuration file in the directory of the application.
+const CONFIG_FILE: &str = "config.toml";
+
+/// The content of the configuration file.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The profile used when none is specified.
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// The default values of the flags. Each field has the name of its flag.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub ether_chain_id: Option<u64>,
+    pub ether_contract_address: Option<String>,
+    pub ether_endpoint: Option<String>,
+    pub ether_owner_priv_key: Option<String>,
+    pub keystore: Option<PathBuf>,
+    pub password_file: Option<PathBuf>,
+    pub mnemonic_file: Option<PathBuf>,
+    pub derivation_path: Option<String>,
+    pub ether_node_account: Option<String>,
+    pub remote_signer: Option<String>,
+    pub remote_signer_account: Option<String>,
+    pub pkcs11_module: Option<PathBuf>,
+    pub pkcs11_slot: Option<u64>,
+    pub pkcs11_label: Option<String>,
+    pub tx_type: Option<String>,
+    /// The fees are in gwei.
+    pub max_fee: Option<f64>,
+    pub priority_fee: Option<f64>,
+    pub gas_price: Option<f64>,
+    pub gas_limit_multiplier: Option<f64>,
+    pub fee_cap: Option<f64>,
+    /// The pending timeout is in seconds.
+    pub pending_timeout: Option<u64>,
+    pub fee_bump: Option<u64>,
+    pub max_replacements: Option<u32>,
+    pub confirmations: Option<u64>,
+    pub finalized: Option<bool>,
+    pub ipfs_endpoint: Option<String>,
+    pub max_attempts: Option<u32>,
+    /// The retry backoff is in milliseconds and the timeouts in seconds.
+    pub retry_backoff: Option<u64>,
+    pub call_timeout: Option<u64>,
+    pub retry_timeout: Option<u64>,
+}
+
+impl Config {
+    /// Loads the configuration file located in `path`. When `path` is `None`, it loads the file
+    /// of the default path, if it exists, otherwise it returns an empty configuration.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let (path, required) = match path {
+            Some(p) => (p.to_path_buf(), true),
+            None => match default_path() {
+                Some(p) => (p, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(err) if err.kind() == ioErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(err) if err.kind() == ioErrorKind::NotFound => {
+                return Err(Error::invalid_arguments(
+                    "path",
+                    "configuration file not found",
+                ))
+            }
+            Err(err) => {
+                return Err(Error::internal(
+                    "system error when reading the configuration file",
+                    BoxError::from(err),
+                ))
+            }
+        };
+
+        toml::from_str(&content).map_err(|err| {
+            Error::invalid_arguments(
+                "path",
+                &format!("invalid configuration file '{}'. {}", path.display(), err),
+            )
+        })
+    }
+
+    /// Returns the profile named `name` or the default profile when `name` is `None`, and its
+    /// name. It returns `None` when `name` is `None` and there isn't a default profile.
+    pub fn profile<'a>(
+        &'a self,
+        name: Option<&'a str>,
+    ) -> Result<Option<(&'a str, &'a Profile)>, Error> {
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+
+        match self.profiles.get(name) {
+            Some(p) => Ok(Some((name, p))),
+            None => Err(Error::invalid_arguments(
+                "profile",
+                &format!("profile '{}' not found in the configuration file", name),
+            )),
+        }
+    }
+}
+
+impl Profile {
+    /// Returns the values of the profile which are set indexed by the ID of their flag.
+    pub fn values(&self) -> Vec<(&'static str, String)> {
+        let mut values = Vec::new();
+        if let Some(v) = self.ether_chain_id {
+            // The ID of the flag has a typo that it's kept for not breaking the command-line.
+            values.push(("ehter_chain_id", v.to_string()));
+        }
+        if let Some(v) = &self.ether_contract_address {
+            values.push(("ether_contract_address", v.clone()));
+        }
+        if let Some(v) = &self.ether_endpoint {
+            values.push(("ether_endpoint", v.clone()));
+        }
+        if let Some(v) = &self.ether_owner_priv_key {
+            values.push(("ether_owner_priv_key", v.clone()));
+        }
+        if let Some(v) = &self.keystore {
+            values.push(("keystore", v.display().to_string()));
+        }
+        if let Some(v) = &self.password_file {
+            values.push(("password_file", v.display().to_string()));
+        }
+        if let Some(v) = &self.mnemonic_file {
+            values.push(("mnemonic_file", v.display().to_string()));
+        }
+        if let Some(v) = &self.derivation_path {
+            values.push(("derivation_path", v.clone()));
+        }
+        if let Some(v) = &self.ether_node_account {
+            values.push(("ether_node_account", v.clone()));
+        }
+        if let Some(v) = &self.remote_signer {
+            values.push(("remote_signer", v.clone()));
+        }
+        if let Some(v) = &self.remote_signer_account {
+            values.push(("remote_signer_account", v.clone()));
+        }
+        if let Some(v) = &self.pkcs11_module {
+            values.push(("pkcs11_module", v.display().to_string()));
+        }
+        if let Some(v) = self.pkcs11_slot {
+            values.push(("pkcs11_slot", v.to_string()));
+        }
+        if let Some(v) = &self.pkcs11_label {
+            values.push(("pkcs11_label", v.clone()));
+        }
+        if let Some(v) = &self.tx_type {
+            values.push(("tx_type", v.clone()));
+        }
+        if let Some(v) = self.max_fee {
+            values.push(("max_fee", v.to_string()));
+        }
+        if let Some(v) = self.priority_fee {
+            values.push(("priority_fee", v.to_string()));
+        }
+        if let Some(v) = self.gas_price {
+            values.push(("gas_price", v.to_string()));
+        }
+        if let Some(v) = self.gas_limit_multiplier {
+            values.push(("gas_limit_multiplier", v.to_string()));
+        }
+        if let Some(v) = self.fee_cap {
+            values.push(("fee_cap", v.to_string()));
+        }
+        if let Some(v) = self.pending_timeout {
+            values.push(("pending_timeout", v.to_string()));
+        }
+        if let Some(v) = self.fee_bump {
+            values.push(("fee_bump", v.to_string()));
+        }
+        if let Some(v) = self.max_replacements {
+            values.push(("max_replacements", v.to_string()));
+        }
+        if let Some(v) = self.confirmations {
+            values.push(("confirmations", v.to_string()));
+        }
+        if let Some(v) = self.finalized {
+            values.push(("finalized", v.to_string()));
+        }
+        if let Some(v) = &self.ipfs_endpoint {
+            values.push(("ipfs_endpoint", v.clone()));
+        }
+        if let Some(v) = self.max_attempts {
+            values.push(("max_attempts", v.to_string()));
+        }
+        if let Some(v) = self.retry_backoff {
+            values.push(("retry_backoff", v.to_string()));
+        }
+        if let Some(v) = self.call_timeout {
+            values.push(("call_timeout", v.to_string()));
+        }
+        if let Some(v) = self.retry_timeout {
+            values.push(("retry_timeout", v.to_string()));
+        }
+
+        values
+    }
+}
+
+/// Returns the default path of the configuration file, which is in the directory of the
+/// application (see [`app_dir`]).
+pub fn default_path() -> Option<PathBuf> {
+    app_dir(CONFIG_FILE)
+}
+
+/// Returns the path of `sub` in the directory of the application, which is the `ipfs-cids-owners`
+/// directory of the user's configuration directory (i.e. `$XDG_CONFIG_HOME` or `$HOME/.config`).
+/// `None` if neither is defined.
+pub fn app_dir(sub: &str) -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|d| d.join(APP_DIR).join(sub))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use mktemp::Temp;
+
+    #[test]
+    fn test_config_load() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            r#"
+default_profile = "local"
+
+[profiles.local]
+ether_chain_id = 1337
+ether_endpoint = "http://localhost:8545"
+tx_type = "legacy"
+gas_price = 1.5
+fee_cap = 20
+pending_timeout = 120
+finalized = true
+max_attempts = 5
+
+[profiles.mainnet]
+ether_contract_address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+keystore = "owner.json"
+password_file = "/run/secrets/password"
+
+[profiles.hsm]
+pkcs11_module = "/usr/lib/softhsm/libsofthsm2.so"
+pkcs11_slot = 0
+pkcs11_label = "owner"
+
+[profiles.signer]
+remote_signer = "http://localhost:9000"
+remote_signer_account = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+"#,
+        )
+        .expect("write config file");
+
+        let config = Config::load(Some(&path)).expect("load config");
+        let (name, local) = config
+            .profile(None)
+            .expect("default profile")
+            .expect("default profile is set");
+        assert_eq!(name, "local", "default profile name");
+        assert_eq!(
+            local.values(),
+            vec![
+                ("ehter_chain_id", String::from("1337")),
+                ("ether_endpoint", String::from("http://localhost:8545")),
+                ("tx_type", String::from("legacy")),
+                ("gas_price", String::from("1.5")),
+                ("fee_cap", String::from("20")),
+                ("pending_timeout", String::from("120")),
+                ("finalized", String::from("true")),
+                ("max_attempts", String::from("5")),
+            ],
+            "default profile values"
+        );
+
+        let (name, mainnet) = config
+            .profile(Some("mainnet"))
+            .expect("named profile")
+            .expect("named profile exists");
+        assert_eq!(name, "mainnet", "named profile name");
+        assert_eq!(
+            mainnet.values(),
+            vec![
+                (
+                    "ether_contract_address",
+                    String::from("0x5FbDB2315678afecb367f032d93F642f64180aa3")
+                ),
+                ("keystore", String::from("owner.json")),
+                ("password_file", String::from("/run/secrets/password")),
+            ],
+            "keystore profile values"
+        );
+
+        let (_, hsm) = config
+            .profile(Some("hsm"))
+            .expect("PKCS#11 profile")
+            .expect("PKCS#11 profile exists");
+        assert_eq!(
+            hsm.values(),
+            vec![
+                (
+                    "pkcs11_module",
+                    String::from("/usr/lib/softhsm/libsofthsm2.so")
+                ),
+                ("pkcs11_slot", String::from("0")),
+                ("pkcs11_label", String::from("owner")),
+            ],
+            "PKCS#11 profile values"
+        );
+
+        let (_, signer) = config
+            .profile(Some("signer"))
+            .expect("remote signer profile")
+            .expect("remote signer profile exists");
+        assert_eq!(
+            signer.values(),
+            vec![
+                ("remote_signer", String::from("http://localhost:9000")),
+                (
+                    "remote_signer_account",
+                    String::from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266")
+                ),
+            ],
+            "remote signer profile values"
+        );
+        assert!(config.profile(Some("sepolia")).is_err(), "unknown profile");
+
+        assert!(
+            Config::load(Some(&dir.join("missing.toml"))).is_err(),
+            "missing config file"
+        );
+
+        fs::write(&path, "[profiles.local]\nunknown = 1\n").expect("write config file");
+        assert!(Config::load(Some(&path)).is_err(), "unknown field");
+    }
+}
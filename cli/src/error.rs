@@ -3,6 +3,8 @@
 use std::error as stderr;
 use std::fmt;
 
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
 /// Convenient type for making more concise wrapping the standard error trait
 /// object into a Box.
 pub type BoxError = Box<dyn stderr::Error + Send + Sync>;
@@ -46,6 +48,71 @@ impl Error {
             error,
         })
     }
+
+    /// Appends `note` to the message of the error, keeping its kind and the errors that caused it.
+    pub(crate) fn with_note(self, note: &str) -> Self {
+        match self {
+            Error::InvalidArguments(mut a) => {
+                a.msg = format!("{}. {}", a.msg, note);
+                Error::InvalidArguments(a)
+            }
+            Error::Internal(mut i) => {
+                i.ctx_msg = format!("{}. {}", i.ctx_msg, note);
+                Error::Internal(i)
+            }
+            Error::External(e) => Error::external(
+                Box::new(Noted {
+                    note: String::from(note),
+                    inner: e.inner,
+                }),
+                e.system,
+            ),
+        }
+    }
+
+    /// Returns the name of the kind of the error, which is the name of its variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::InvalidArguments(_) => "InvalidArguments",
+            Error::Internal(_) => "Internal",
+            Error::External(_) => "External",
+        }
+    }
+
+    /// Returns the exit code of the application corresponding to the kind of the error. The exit
+    /// codes are documented in the help of the command-line application. 2 isn't used because it's
+    /// the exit code of the usage errors reported by the flags parser.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidArguments(_) => 5,
+            Error::External(_) => 3,
+            Error::Internal(_) => 4,
+        }
+    }
+}
+
+/// Serializes the error with its kind, its message, the invalid arguments names or the external
+/// system depending of its kind, and the messages of the chain of errors that caused it.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut sources = Vec::new();
+        let mut source = stderr::Error::source(self);
+        while let Some(s) = source {
+            sources.push(s.to_string());
+            source = s.source();
+        }
+
+        let mut state = serializer.serialize_struct("Error", 4)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        match self {
+            Error::InvalidArguments(a) => state.serialize_field("names", &a.names)?,
+            Error::External(e) => state.serialize_field("system", &e.system.to_string())?,
+            Error::Internal(_) => {}
+        }
+        state.serialize_field("sources", &sources)?;
+        state.end()
+    }
 }
 
 impl stderr::Error for Error {
@@ -194,13 +261,37 @@ impl fmt::Display for External {
     }
 }
 
+/// An error reported by an external system with a note appended by the application.
+#[derive(Debug)]
+struct Noted {
+    note: String,
+    inner: BoxError,
+}
+
+impl stderr::Error for Noted {
+    fn source(&self) -> Option<&(dyn stderr::Error + 'static)> {
+        self.inner.source()
+    }
+}
+
+impl fmt::Display for Noted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}. {}", self.inner, self.note)
+    }
+}
+
 /// Indicates the external system that has reported the error.
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq)]
 pub enum ExternalSystem {
     /// Ethereum error.
     Ethereum,
     /// IPFS error.
     IPFS,
+    /// Remote signer error.
+    Signer,
+    /// PKCS#11 token (e.g. HSM) error.
+    Pkcs11,
 }
 
 impl fmt::Display for ExternalSystem {
@@ -208,6 +299,88 @@ impl fmt::Display for ExternalSystem {
         match self {
             ExternalSystem::Ethereum => write!(f, "Ethereum"),
             ExternalSystem::IPFS => write!(f, "IPFS"),
+            ExternalSystem::Signer => write!(f, "remote signer"),
+            ExternalSystem::Pkcs11 => write!(f, "PKCS#11 token"),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_serialize() {
+        let err = Error::invalid_arguments("cid", "invalid CID format");
+        assert_eq!(err.exit_code(), 5, "invalid arguments exit code");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "InvalidArguments",
+                "message": "cid arguments have invalid values. invalid CID format",
+                "names": "cid",
+                "sources": [],
+            }),
+            "invalid arguments"
+        );
+
+        let err = Error::external(BoxError::from("connection refused"), ExternalSystem::IPFS);
+        assert_eq!(err.exit_code(), 3, "external exit code");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "External",
+                "message": "External error produced by the IPFS system. connection refused",
+                "system": "IPFS",
+                "sources": ["connection refused"],
+            }),
+            "external"
+        );
+
+        let err = Error::internal("system error", BoxError::from("disk full"));
+        assert_eq!(err.exit_code(), 4, "internal exit code");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "Internal",
+                "message": "system error",
+                "sources": ["disk full"],
+            }),
+            "internal"
+        );
+    }
+
+    #[test]
+    fn test_error_with_note() {
+        let err = Error::invalid_arguments("cid", "invalid CID format").with_note("note");
+        assert_eq!(
+            err.to_string(),
+            "cid arguments have invalid values. invalid CID format. note",
+            "invalid arguments"
+        );
+
+        let err = Error::external(BoxError::from("connection refused"), ExternalSystem::IPFS)
+            .with_note("note");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "External",
+                "message": "External error produced by the IPFS system. connection refused. note",
+                "system": "IPFS",
+                "sources": ["connection refused. note"],
+            }),
+            "external"
+        );
+
+        let err = Error::internal("system error", BoxError::from("disk full")).with_note("note");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "Internal",
+                "message": "system error. note",
+                "sources": ["disk full"],
+            }),
+            "internal"
+        );
+    }
+}
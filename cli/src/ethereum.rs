@@ -1,34 +1,75 @@
-use crate::error::{BoxError, Error, ExternalSystem};
+use crate::{
+    error::{BoxError, Error, ExternalSystem},
+    lock::OwnerLock,
+    pkcs11::Pkcs11Signer,
+    retry::{RetryError, RetryPolicy},
+    signer::RemoteSigner,
+};
 
+use std::fmt::Debug;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use async_trait::async_trait;
 use ethers::{
-    core::types::{Address, TransactionReceipt},
+    abi::AbiDecode,
+    contract::builders::ContractCall,
+    core::types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+        Transaction, TransactionReceipt, TransactionRequest, TxHash, H256, U256, U64,
+    },
     middleware::SignerMiddleware,
-    providers::{Http, Provider},
+    providers::{Http, HttpClientError, JsonRpcClient, Middleware, Provider, ProviderError},
     signers::{LocalWallet, Signer},
 };
+use futures::future;
 use ipfs_cids_owners_contracts::cids_owners;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Mutex;
+
+/// Evaluates `$body` with `$contract` bound to the instance of the contract of the
+/// [`OwnerContract`] `$owner_contract`. The type of the contract depends on the kind of owner,
+/// hence each kind has its own expansion of `$body`.
+macro_rules! with_owner_contract {
+    ($owner_contract:expr, |$contract:ident| $body:expr) => {
+        match $owner_contract {
+            OwnerContract::Wallet($contract) => $body,
+            OwnerContract::Remote($contract) => $body,
+            OwnerContract::Pkcs11($contract) => $body,
+            OwnerContract::Node($contract) => $body,
+        }
+    };
+}
 
 /// CIDsOnwers expose a high level API for registering CIDs to the CIDsOwners smart contract.
+#[derive(Clone)]
 pub struct CIDsOwners {
     contract_address: Address,
-    provider: Provider<Http>,
+    provider: Provider<RetryHttp>,
     chain_id: u64,
+    gas: GasOptions,
+    finality: Finality,
 }
 
 impl CIDsOwners {
-    /// Create a new instance.
+    /// Create a new instance whose calls to the Ethereum node are retried and timed out with
+    /// `retry`.
     /// When `chain_id` is `None`, 1 is used (i.e. Ethereum mainnet).
     pub fn new(
         contract_address: Address,
         endpoint: &str,
         chain_id: Option<u64>,
+        retry: RetryPolicy,
     ) -> Result<Self, Error> {
-        let chain_id = if let Some(id) = chain_id { id } else { 1 };
+        let chain_id = chain_id.unwrap_or(1);
 
-        let provider = Provider::<Http>::try_from(endpoint)
+        let http = Http::from_str(endpoint)
             .map_err(|_| Error::invalid_arguments("endpoint", "malformed HTTP address"))?;
+        let provider = Provider::new(RetryHttp {
+            http,
+            policy: retry,
+        });
 
         // TODO: this constructor should validate `endppoint` and `contract_address` to report the
         // caller any problem with them rather than creating the instance and then finding the
@@ -38,94 +79,1227 @@ impl CIDsOwners {
             contract_address,
             provider,
             chain_id,
+            gas: GasOptions::default(),
+            finality: Finality::default(),
         })
     }
 
-    // Register `cid` to the `owner` wallet.
-    pub async fn register_cid_owner(
+    /// Set the options for the type, the fees and the gas limit of the transactions.
+    pub fn with_gas_options(mut self, gas: GasOptions) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    /// Set when a mined transaction is considered final by [`Session::wait`].
+    pub fn with_finality(mut self, finality: Finality) -> Self {
+        self.finality = finality;
+        self
+    }
+
+    // Start a registration session of `owner`, which sends its transactions. `lock` must be the
+    // lock of the owner, which is held until the session is dropped.
+    pub fn session(&self, owner: Owner, lock: OwnerLock) -> Session {
+        let address = owner.address();
+        let contract = match owner {
+            Owner::Wallet(w) => OwnerContract::Wallet(self.signer_contract(w)),
+            Owner::Remote(s) => OwnerContract::Remote(self.signer_contract(s)),
+            Owner::Pkcs11(s) => OwnerContract::Pkcs11(self.signer_contract(s)),
+            Owner::Node(a) => OwnerContract::Node(self.node_contract(a)),
+        };
+
+        Session {
+            cids_owners: self.clone(),
+            contract,
+            address,
+            nonce: Mutex::new(None),
+            _lock: lock,
+        }
+    }
+
+    // Get the status of the transaction identified by `hash` and the CID that it registers when
+    // it's a call to the register method of the contract.
+    pub async fn transaction_status(&self, hash: TxHash) -> Result<TxStatus, Error> {
+        let map_err = |err| Error::external(BoxError::from(err), ExternalSystem::Ethereum);
+
+        let tx = self
+            .provider
+            .get_transaction(hash)
+            .await
+            .map_err(map_err)?
+            .ok_or_else(|| {
+                Error::invalid_arguments(
+                    "hash",
+                    "transaction not found, it may have never been sent or it has been dropped",
+                )
+            })?;
+
+        let cid = self.registered_cid(&tx);
+
+        let mut status = TxStatus {
+            state: TxState::Pending,
+            from: tx.from,
+            nonce: tx.nonce.as_u64(),
+            block_number: None,
+            confirmations: 0,
+            cid,
+        };
+
+        if tx.block_number.is_none() {
+            return Ok(status);
+        }
+
+        let receipt = match self
+            .provider
+            .get_transaction_receipt(hash)
+            .await
+            .map_err(map_err)?
+        {
+            Some(r) => r,
+            // The transaction has just been mined and the node doesn't have the receipt yet.
+            None => return Ok(status),
+        };
+
+        let current_block = self.provider.get_block_number().await.map_err(map_err)?;
+        if let Some(block) = receipt.block_number {
+            status.block_number = Some(block.as_u64());
+            status.confirmations = (current_block + 1).saturating_sub(block).as_u64();
+        }
+
+        status.state = if receipt.status == Some(U64::from(1)) {
+            TxState::Mined
+        } else {
+            TxState::Failed
+        };
+
+        Ok(status)
+    }
+
+    // Return the CID that `tx` registers when it's a call to the register method of the contract.
+    fn registered_cid(&self, tx: &Transaction) -> Option<String> {
+        if tx.to != Some(self.contract_address) {
+            return None;
+        }
+
+        match cids_owners::CIDsOwnersCalls::decode(&tx.input) {
+            Ok(cids_owners::CIDsOwnersCalls::Register(call)) => Some(call.cid),
+            _ => None,
+        }
+    }
+
+    // Return the mined transaction of `address` with `nonce`, which was sent after the block
+    // number `from_block` was mined. `None` when the nonce isn't used by any mined transaction yet.
+    // The nonce is used when the latest nonce of `address` is greater; then, the transaction is
+    // searched in the blocks mined since `from_block`, so the node doesn't need to keep the state
+    // of old blocks.
+    async fn mined_transaction(
         &self,
-        cid: &str,
-        owner: LocalWallet,
+        address: Address,
+        nonce: U256,
+        from_block: u64,
+    ) -> Result<Option<Transaction>, Error> {
+        let map_err = |err| Error::external(BoxError::from(err), ExternalSystem::Ethereum);
+
+        // The nonce is fetched before the block number for searching up to the block where it was
+        // used.
+        let latest_nonce = self
+            .provider
+            .get_transaction_count(address, None)
+            .await
+            .map_err(map_err)?;
+        if latest_nonce <= nonce {
+            return Ok(None);
+        }
+
+        let latest = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(map_err)?
+            .as_u64();
+        for number in from_block..=latest {
+            let block = self
+                .provider
+                .get_block_with_txs(number)
+                .await
+                .map_err(map_err)?;
+            if let Some(tx) = block.and_then(|b| {
+                b.transactions
+                    .into_iter()
+                    .find(|tx| tx.from == address && tx.nonce == nonce)
+            }) {
+                return Ok(Some(tx));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Return the receipt of the first transaction of `hashes` which is mined. `None` when none of
+    // them is mined yet.
+    async fn mined_receipt(&self, hashes: &[TxHash]) -> Result<Option<TransactionReceipt>, Error> {
+        for hash in hashes {
+            let receipt = self
+                .provider
+                .get_transaction_receipt(*hash)
+                .await
+                .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?;
+
+            if let Some(r) = receipt.filter(|r| r.block_number.is_some()) {
+                return Ok(Some(r));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Return true when the node knows any of the transactions of `hashes`, so they haven't been
+    // dropped from its mempool.
+    async fn any_known(&self, hashes: &[TxHash]) -> Result<bool, Error> {
+        for hash in hashes {
+            let tx =
+                self.provider.get_transaction(*hash).await.map_err(|err| {
+                    Error::external(BoxError::from(err), ExternalSystem::Ethereum)
+                })?;
+
+            if tx.is_some() {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    // Wait until the block of the mined transaction of `receipt` reaches the finality of the
+    // instance and return its receipt.
+    // After reaching the finality, it checks that the transaction is still in the canonical chain
+    // and it returns an external error whose origin is `Reorg` when it isn't. When the
+    // transaction was mined again in another block, it waits until that block reaches the
+    // finality.
+    async fn wait_final(
+        &self,
+        mut receipt: TransactionReceipt,
     ) -> Result<TransactionReceipt, Error> {
-        let owner = owner.with_chain_id(self.chain_id);
+        let hash = receipt.transaction_hash;
 
-        let client = SignerMiddleware::new(self.provider.clone(), owner);
-        let client = Arc::new(client);
+        loop {
+            let block_number = receipt.block_number.unwrap_or_default();
+            while !self.is_final(block_number).await? {
+                tokio::time::sleep(self.provider.get_interval()).await;
+            }
+
+            match self
+                .provider
+                .get_transaction_receipt(hash)
+                .await
+                .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?
+            {
+                Some(r) if r.block_hash == receipt.block_hash => return Ok(receipt),
+                Some(r) if r.block_number.is_some() => receipt = r,
+                _ => {
+                    return Err(Error::external(
+                        BoxError::from(Reorg {
+                            tx_hash: hash,
+                            block_number: block_number.as_u64(),
+                            block_hash: receipt.block_hash.unwrap_or_default(),
+                        }),
+                        ExternalSystem::Ethereum,
+                    ))
+                }
+            }
+        }
+    }
+
+    // Simulate registering `cid` to the `owner` account with `eth_call` and return the gas that
+    // the transaction would use, without sending it.
+    pub async fn estimate_register_cid_owner(
+        &self,
+        cid: &str,
+        owner: Address,
+    ) -> Result<U256, Error> {
+        let map_err = |err| Error::external(BoxError::from(err), ExternalSystem::Ethereum);
+
+        let client = Arc::new(self.provider.clone());
         let contract = cids_owners::CIDsOwners::new(self.contract_address, client);
+        let call = contract.register(String::from(cid)).from(owner);
 
-        let receipt = contract
-            .register(String::from(cid))
-            .send()
-            .await
-            .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?
+        call.call().await.map_err(map_err)?;
+        call.estimate_gas().await.map_err(map_err)
+    }
+
+    // Check that there is a contract deployed at the contract address, because the calls to an
+    // address without code succeed without doing anything.
+    pub async fn check_contract(&self) -> Result<(), Error> {
+        let code = self
+            .provider
+            .get_code(self.contract_address, None)
             .await
             .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?;
 
-        Ok(receipt.expect("always expecting a transaction receipt from the register method of the CIDsOwners contracdt"))
+        if code.as_ref().is_empty() {
+            return Err(Error::invalid_arguments(
+                "contract_address",
+                "there isn't any contract deployed at the address",
+            ));
+        }
+
+        Ok(())
     }
 
-    // Get the registered CIDs from `owner`.
-    // Currently isn't exposed publicly because only tests uses it and it isn't part of the
-    // requirements.
-    #[allow(dead_code)]
-    pub(crate) async fn my_registered_cids(
-        &self,
-        owner: LocalWallet,
-    ) -> Result<std::vec::Vec<String>, Error> {
-        let owner = owner.with_chain_id(self.chain_id);
-        let owner_address = owner.address();
+    // Get the fee per gas in wei that the transactions would pay with the current gas options,
+    // which is the maximum one for type-2 transactions. It fails when it exceeds the fee cap.
+    pub async fn fee_per_gas(&self) -> Result<U256, Error> {
+        Ok(self.fees().await?.fee_per_gas())
+    }
 
-        let client = SignerMiddleware::new(self.provider.clone(), owner);
-        let client = Arc::new(client);
+    // Get the registered CIDs from `owner`.
+    // It only reads the state of the contract, hence it doesn't require any wallet.
+    pub async fn registered_cids(&self, owner: Address) -> Result<std::vec::Vec<String>, Error> {
+        let client = Arc::new(self.provider.clone());
         let contract = cids_owners::CIDsOwners::new(self.contract_address, client);
 
         let cids = contract
-            .get_owned_ci_ds(owner_address)
+            .get_owned_ci_ds(owner)
             .call()
             .await
             .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?;
 
         Ok(cids)
     }
+
+    // Returns the call for registering `cid` through `contract` with the type, the fees and the
+    // gas limit of the gas options, estimating the ones which aren't set.
+    async fn register_call<M: Middleware + 'static>(
+        &self,
+        contract: &cids_owners::CIDsOwners<M>,
+        cid: &str,
+    ) -> Result<ContractCall<M, ()>, Error> {
+        let mut call = contract.register(String::from(cid));
+
+        match self.fees().await? {
+            Fees::Legacy { gas_price } => call = call.legacy().gas_price(gas_price),
+            Fees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                if let TypedTransaction::Eip1559(tx) = &mut call.tx {
+                    tx.max_fee_per_gas = Some(max_fee_per_gas);
+                    tx.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+                }
+            }
+        }
+
+        if self.gas.gas_limit_multiplier != 1.0 {
+            let gas = call
+                .estimate_gas()
+                .await
+                .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?;
+            // The multiplier is applied with a precision of 3 decimals.
+            let multiplier = (self.gas.gas_limit_multiplier * 1000.0).ceil() as u64;
+            call = call.gas(gas * multiplier / 1000);
+        }
+
+        Ok(call)
+    }
+
+    // Returns the fees of the transactions with the gas options, estimating the ones which aren't
+    // set. It fails when the fee per gas exceeds the fee cap.
+    async fn fees(&self) -> Result<Fees, Error> {
+        let map_err = |err| Error::external(BoxError::from(err), ExternalSystem::Ethereum);
+
+        let fees = match self.gas.tx_type {
+            TxType::Legacy => Fees::Legacy {
+                gas_price: match self.gas.gas_price {
+                    Some(p) => p,
+                    None => self.provider.get_gas_price().await.map_err(map_err)?,
+                },
+            },
+            TxType::Eip1559 => {
+                let (max_fee, priority_fee) =
+                    match (self.gas.max_fee_per_gas, self.gas.max_priority_fee_per_gas) {
+                        (Some(m), Some(p)) => (m, p),
+                        (max_fee, priority_fee) => {
+                            let (est_max_fee, est_priority_fee) = self
+                                .provider
+                                .estimate_eip1559_fees(None)
+                                .await
+                                .map_err(map_err)?;
+
+                            match (max_fee, priority_fee) {
+                                (Some(m), None) => (m, est_priority_fee.min(m)),
+                                // Keep the estimated margin for the base fee.
+                                (None, Some(p)) => {
+                                    (est_max_fee.saturating_sub(est_priority_fee) + p, p)
+                                }
+                                _ => (est_max_fee, est_priority_fee),
+                            }
+                        }
+                    };
+
+                if priority_fee > max_fee {
+                    return Err(Error::invalid_arguments(
+                        "max_priority_fee_per_gas",
+                        "the priority fee cannot be greater than the maximum fee",
+                    ));
+                }
+
+                Fees::Eip1559 {
+                    max_fee_per_gas: max_fee,
+                    max_priority_fee_per_gas: priority_fee,
+                }
+            }
+        };
+
+        self.check_fee_cap(fees.fee_per_gas())?;
+        Ok(fees)
+    }
+
+    // Returns an error when `fee_per_gas` exceeds the fee cap of the gas options.
+    fn check_fee_cap(&self, fee_per_gas: U256) -> Result<(), Error> {
+        match self.gas.fee_cap {
+            Some(cap) if fee_per_gas > cap => Err(Error::invalid_arguments(
+                "fee_cap",
+                &format!(
+                    "the fee per gas of {} wei exceeds the fee cap of {} wei",
+                    fee_per_gas, cap
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Returns the transaction which replaces the pending `tx` with the same nonce, which is of
+    // the same type and whose fees are the ones of `tx` increased by the fee bump of the gas
+    // options or the current estimated ones when they are higher. It fails when the fee per gas
+    // exceeds the fee cap.
+    async fn replacement(
+        &self,
+        tx: &Transaction,
+        kind: Replacement,
+    ) -> Result<TypedTransaction, Error> {
+        let map_err = |err| Error::external(BoxError::from(err), ExternalSystem::Ethereum);
+        // The nodes only accept a replacement whose fees are higher than the replaced ones.
+        let bump = |fee: U256| (fee * (100 + self.gas.fee_bump_percent) / 100).max(fee + 1);
+
+        let request = TransactionRequest::new().from(tx.from).nonce(tx.nonce);
+        let request = match kind {
+            Replacement::SpeedUp => {
+                let request = request.value(tx.value).data(tx.input.clone()).gas(tx.gas);
+                match tx.to {
+                    Some(to) => request.to(to),
+                    None => request,
+                }
+            }
+            // A transfer of nothing to the sender itself, with the gas of a plain transfer.
+            Replacement::Cancel => request.to(tx.from).value(0).gas(21_000),
+        };
+
+        if tx.transaction_type == Some(U64::from(2)) {
+            let (est_max_fee, est_priority_fee) = self
+                .provider
+                .estimate_eip1559_fees(None)
+                .await
+                .map_err(map_err)?;
+            let priority_fee =
+                bump(tx.max_priority_fee_per_gas.unwrap_or_default()).max(est_priority_fee);
+            let max_fee = bump(tx.max_fee_per_gas.unwrap_or_default())
+                .max(est_max_fee)
+                .max(priority_fee);
+            self.check_fee_cap(max_fee)?;
+
+            let request: Eip1559TransactionRequest = TypedTransaction::Legacy(request).into();
+            Ok(request
+                .max_fee_per_gas(max_fee)
+                .max_priority_fee_per_gas(priority_fee)
+                .into())
+        } else {
+            let est_gas_price = self.provider.get_gas_price().await.map_err(map_err)?;
+            let gas_price = bump(tx.gas_price.unwrap_or_default()).max(est_gas_price);
+            self.check_fee_cap(gas_price)?;
+
+            Ok(request.gas_price(gas_price).into())
+        }
+    }
+
+    // Returns true when the block with `block_number` reached the finality of the instance.
+    async fn is_final(&self, block_number: U64) -> Result<bool, Error> {
+        let map_err = |err| Error::external(BoxError::from(err), ExternalSystem::Ethereum);
+
+        match self.finality {
+            Finality::Confirmations(n) => {
+                let current = self.provider.get_block_number().await.map_err(map_err)?;
+                Ok((current + 1).saturating_sub(block_number) >= U64::from(n))
+            }
+            Finality::Finalized => {
+                let finalized = self
+                    .provider
+                    .get_block(BlockNumber::Finalized)
+                    .await
+                    .map_err(map_err)?
+                    .and_then(|b| b.number);
+                Ok(finalized.is_some_and(|f| f >= block_number))
+            }
+        }
+    }
+
+    // Returns the pending nonce of `address`, which is the nonce of its next transaction
+    // considering the ones which aren't mined yet.
+    async fn pending_nonce(&self, address: Address) -> Result<U256, Error> {
+        self.provider
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))
+    }
+
+    // Returns an instance of the contract which signs the transactions with `owner`.
+    fn signer_contract<S: Signer + 'static>(
+        &self,
+        owner: S,
+    ) -> cids_owners::CIDsOwners<SignerMiddleware<Provider<RetryHttp>, S>> {
+        let owner = owner.with_chain_id(self.chain_id);
+
+        let client = SignerMiddleware::new(self.provider.clone(), owner);
+        let client = Arc::new(client);
+        cids_owners::CIDsOwners::new(self.contract_address, client)
+    }
+
+    // Returns an instance of the contract which sends the transactions from the `owner` account
+    // managed by the Ethereum node, which signs them.
+    fn node_contract(&self, owner: Address) -> cids_owners::CIDsOwners<Provider<RetryHttp>> {
+        let client = Arc::new(self.provider.clone().with_sender(owner));
+        cids_owners::CIDsOwners::new(self.contract_address, client)
+    }
+}
+
+/// A registration session of an owner. It keeps the client which sends the transactions of the
+/// owner and assigns their nonces locally, so the transactions are sent back to back without
+/// waiting until the previous ones are mined.
+///
+/// The nonce of the first transaction is the pending nonce of the owner reported by the Ethereum
+/// node, which is fetched again after a transaction fails to be sent. The session holds the lock
+/// of the owner, so other processes don't send transactions of the owner at the same time.
+pub struct Session {
+    cids_owners: CIDsOwners,
+    contract: OwnerContract,
+    address: Address,
+    /// The nonce of the next transaction; `None` when it has to be fetched from the node.
+    nonce: Mutex<Option<U256>>,
+    _lock: OwnerLock,
+}
+
+impl Session {
+    /// Sends the transaction for registering `cid` and returns its hash without waiting until
+    /// it's mined.
+    pub async fn send_register(&self, cid: &str) -> Result<TxHash, Error> {
+        self.send_register_with_nonce(cid, |_, _| Ok(())).await
+    }
+
+    /// Like [`Self::send_register`] but it calls `on_nonce` with the nonce of the transaction and
+    /// the number of the latest block before sending it, so they can be recorded in case that the
+    /// transaction is sent but its hash is lost. The transaction isn't sent when `on_nonce` fails.
+    pub async fn send_register_with_nonce(
+        &self,
+        cid: &str,
+        on_nonce: impl FnOnce(U256, u64) -> Result<(), Error>,
+    ) -> Result<TxHash, Error> {
+        with_owner_contract!(&self.contract, |contract| {
+            let mut call = self.cids_owners.register_call(contract, cid).await?;
+
+            // The lock is held until the transaction is sent for sending the transactions in
+            // the order of their nonces.
+            let mut next_nonce = self.nonce.lock().await;
+            let nonce = match *next_nonce {
+                Some(n) => n,
+                None => self.cids_owners.pending_nonce(self.address).await?,
+            };
+            call.tx.set_nonce(nonce);
+            let block = self
+                .cids_owners
+                .provider
+                .get_block_number()
+                .await
+                .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?;
+            on_nonce(nonce, block.as_u64())?;
+
+            match send_register(call).await {
+                Ok(hash) => {
+                    *next_nonce = Some(nonce + 1);
+                    Ok(hash)
+                }
+                Err(err) => {
+                    // The nonce may have not been used or it may have been used by another
+                    // application.
+                    *next_nonce = None;
+                    Err(err)
+                }
+            }
+        })
+    }
+
+    /// Finds the transaction for registering `cid` which was sent with `nonce` after the block
+    /// number `from_block`, when it's unknown if it was sent, and returns its hash. `hash` is the
+    /// hash of the transaction, if it's known. `nonce` and `from_block` are the ones passed to
+    /// the `on_nonce` callback of [`Self::send_register_with_nonce`].
+    /// It returns `None` when the transaction wasn't sent, it was dropped or reverted, or the nonce
+    /// was used by another transaction, so it has to be sent again.
+    ///
+    /// It fails with an external error when a transaction with the nonce is pending but it isn't
+    /// the one identified by `hash`, because it cannot know if it registers `cid` until it's
+    /// mined.
+    pub async fn find_register(
+        &self,
+        cid: &str,
+        nonce: U256,
+        from_block: u64,
+        hash: Option<TxHash>,
+    ) -> Result<Option<TxHash>, Error> {
+        if let Some(h) = hash {
+            if self.cids_owners.any_known(&[h]).await? {
+                // A reverted transaction didn't register the CID.
+                return match self.cids_owners.mined_receipt(&[h]).await? {
+                    Some(r) if r.status != Some(U64::from(1)) => Ok(None),
+                    _ => Ok(Some(h)),
+                };
+            }
+        }
+
+        // The transaction may have been replaced or its hash may have not been recorded.
+        if let Some(tx) = self
+            .cids_owners
+            .mined_transaction(self.address, nonce, from_block)
+            .await?
+        {
+            if self.cids_owners.registered_cid(&tx).as_deref() != Some(cid) {
+                return Ok(None);
+            }
+
+            return match self.cids_owners.mined_receipt(&[tx.hash]).await? {
+                Some(r) if r.status == Some(U64::from(1)) => Ok(Some(tx.hash)),
+                _ => Ok(None),
+            };
+        }
+
+        if self.cids_owners.pending_nonce(self.address).await? > nonce {
+            return Err(Error::external(
+                BoxError::from(format!(
+                    "the transaction with the nonce {} of the owner {:?} is pending and it's \
+                     unknown if it registers the CID '{}', retry when it's mined",
+                    nonce, self.address, cid
+                )),
+                ExternalSystem::Ethereum,
+            ));
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the address of the owner of the session.
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Registers `cid` and waits until the transaction is mined.
+    pub async fn register(&self, cid: &str) -> Result<TransactionReceipt, Error> {
+        let hash = self.send_register(cid).await?;
+        self.wait(hash).await
+    }
+
+    /// Waits until the transaction identified by `hash`, or one of the transactions which replace
+    /// it, is mined and its block reaches the finality, and returns its receipt.
+    ///
+    /// When the gas options have a pending timeout, the transaction is replaced by another one
+    /// with higher fees each time that the timeout expires, until the maximum number of
+    /// replacements; then, it fails with an external error whose origin is [`Stuck`]. It fails
+    /// with an external error whose origin is [`Dropped`] when the transactions are dropped from
+    /// the mempool of the node without being mined, with one whose origin is [`Reorg`] when a
+    /// chain reorganization removes the mined transaction, and with one whose origin is
+    /// [`Reverted`] when the mined transaction failed.
+    pub async fn wait(&self, hash: TxHash) -> Result<TransactionReceipt, Error> {
+        self.wait_any(&[hash]).await
+    }
+
+    /// Like [`Self::wait`] but it waits until any of the transactions identified by `hashes`,
+    /// which must have the same nonce, is mined. The last one is the replaced one when the pending
+    /// timeout expires.
+    pub async fn wait_any(&self, hashes: &[TxHash]) -> Result<TransactionReceipt, Error> {
+        let gas = &self.cids_owners.gas;
+        let mut hashes = hashes.to_vec();
+        let mut replacements = 0;
+        let mut pending_since = Instant::now();
+
+        let receipt = loop {
+            if let Some(r) = self.cids_owners.mined_receipt(&hashes).await? {
+                break r;
+            }
+
+            let last = *hashes.last().expect("BUG waiting for no transaction");
+            if !self.cids_owners.any_known(&hashes).await? {
+                return Err(Error::external(
+                    BoxError::from(Dropped { tx_hash: last }),
+                    ExternalSystem::Ethereum,
+                ));
+            }
+
+            if gas
+                .pending_timeout
+                .is_some_and(|t| pending_since.elapsed() >= t)
+            {
+                if replacements == gas.max_replacements {
+                    return Err(Error::external(
+                        BoxError::from(Stuck {
+                            tx_hash: last,
+                            replacements,
+                        }),
+                        ExternalSystem::Ethereum,
+                    ));
+                }
+
+                match self.replace(last, Replacement::SpeedUp).await {
+                    Ok(hash) => {
+                        hashes.push(hash);
+                        replacements += 1;
+                        pending_since = Instant::now();
+                    }
+                    // The transaction may have been mined after checking it.
+                    Err(err) => match self.cids_owners.mined_receipt(&hashes).await? {
+                        Some(r) => break r,
+                        None => return Err(err),
+                    },
+                }
+            }
+
+            tokio::time::sleep(self.cids_owners.provider.get_interval()).await;
+        };
+
+        let receipt = self.cids_owners.wait_final(receipt).await?;
+        if receipt.status != Some(U64::from(1)) {
+            return Err(Error::external(
+                BoxError::from(Reverted {
+                    tx_hash: receipt.transaction_hash,
+                }),
+                ExternalSystem::Ethereum,
+            ));
+        }
+
+        Ok(receipt)
+    }
+
+    /// Sends a transaction which replaces the pending transaction of the owner identified by
+    /// `hash`, with the same nonce and higher fees, and returns its hash without waiting until
+    /// it's mined. See [`Replacement`].
+    pub async fn replace(&self, hash: TxHash, kind: Replacement) -> Result<TxHash, Error> {
+        let tx = self
+            .cids_owners
+            .provider
+            .get_transaction(hash)
+            .await
+            .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?
+            .ok_or_else(|| {
+                Error::invalid_arguments(
+                    "hash",
+                    "transaction not found, it may have never been sent or it has been dropped",
+                )
+            })?;
+
+        if tx.from != self.address {
+            return Err(Error::invalid_arguments(
+                "hash",
+                &format!(
+                    "the transaction is sent by {:?}, which isn't the owner {:?}",
+                    tx.from, self.address
+                ),
+            ));
+        }
+
+        if tx.block_number.is_some() {
+            return Err(Error::invalid_arguments(
+                "hash",
+                "the transaction is already mined",
+            ));
+        }
+
+        let replacement = self.cids_owners.replacement(&tx, kind).await?;
+        with_owner_contract!(&self.contract, |contract| {
+            let client = contract.client();
+            let pending = client
+                .send_transaction(replacement, None)
+                .await
+                .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?;
+
+            Ok(pending.tx_hash())
+        })
+    }
+
+    /// Registers each CID of `cids`. The transactions are sent back to back and then it waits
+    /// until all of them are mined.
+    /// It returns the result of each registration in the same order than `cids`, so failing to
+    /// register one CID doesn't prevent registering the rest.
+    pub async fn register_cids(&self, cids: &[String]) -> Vec<Result<TransactionReceipt, Error>> {
+        let mut sent = Vec::with_capacity(cids.len());
+        for cid in cids {
+            sent.push(self.send_register(cid).await);
+        }
+
+        future::join_all(sent.into_iter().map(|res| async move {
+            match res {
+                Ok(hash) => self.wait(hash).await,
+                Err(err) => Err(err),
+            }
+        }))
+        .await
+    }
+}
+
+/// The instance of the contract which sends the transactions of an owner for each kind of owner.
+enum OwnerContract {
+    Wallet(cids_owners::CIDsOwners<SignerMiddleware<Provider<RetryHttp>, LocalWallet>>),
+    Remote(cids_owners::CIDsOwners<SignerMiddleware<Provider<RetryHttp>, RemoteSigner>>),
+    Pkcs11(cids_owners::CIDsOwners<SignerMiddleware<Provider<RetryHttp>, Pkcs11Signer>>),
+    Node(cids_owners::CIDsOwners<Provider<RetryHttp>>),
+}
+
+/// JSON-RPC client over HTTP which retries and times out the requests with a retry policy.
+#[derive(Clone, Debug)]
+pub struct RetryHttp {
+    http: Http,
+    policy: RetryPolicy,
+}
+
+#[async_trait]
+impl JsonRpcClient for RetryHttp {
+    type Error = RetryError<HttpClientError>;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let sends_tx = matches!(method, "eth_sendRawTransaction" | "eth_sendTransaction");
+        self.policy
+            .retry(
+                || self.http.request(method, &params),
+                |err| transient_rpc_error(err, sends_tx),
+            )
+            .await
+    }
+}
+
+impl From<RetryError<HttpClientError>> for ProviderError {
+    fn from(err: RetryError<HttpClientError>) -> Self {
+        match err {
+            RetryError::Failed(err) => err.into(),
+            exhausted => ProviderError::JsonRpcClientError(Box::new(exhausted)),
+        }
+    }
+}
+
+/// Returns true when the failure of an attempt of a JSON-RPC request is transient, so it's
+/// retried. `err` is `None` when the attempt timed out.
+/// The requests which send a transaction (i.e. `sends_tx`) are only retried when the node didn't
+/// receive them, because the node may have received the transaction otherwise and sending it
+/// again would fail.
+fn transient_rpc_error(err: Option<&HttpClientError>, sends_tx: bool) -> bool {
+    match err {
+        None => !sends_tx,
+        Some(HttpClientError::ReqwestError(err)) => {
+            err.is_connect()
+                || (!sends_tx && (err.is_timeout() || err.is_request() || err.is_body()))
+        }
+        // The node rejected the request because of its rate limit.
+        Some(HttpClientError::JsonRpcError(err)) => {
+            err.code == 429
+                || err.code == -32005
+                || err.message.to_lowercase().contains("rate limit")
+        }
+        // A response which isn't JSON-RPC, e.g. the error page of a proxy.
+        Some(HttpClientError::SerdeJson { .. }) => !sends_tx,
+    }
+}
+
+/// The CIDs' owner account and how its transactions are signed.
+#[derive(Clone, Debug)]
+pub enum Owner {
+    /// The private key of the account is available locally.
+    Wallet(LocalWallet),
+    /// The private key of the account is held by a remote signer.
+    Remote(RemoteSigner),
+    /// The private key of the account is held by a PKCS#11 token (e.g. an HSM).
+    Pkcs11(Pkcs11Signer),
+    /// The account is managed (i.e. unlocked) by the Ethereum node, which signs the transactions
+    /// sent through `eth_sendTransaction`.
+    Node(Address),
+}
+
+impl Owner {
+    /// Returns the address of the account.
+    pub fn address(&self) -> Address {
+        match self {
+            Owner::Wallet(w) => w.address(),
+            Owner::Remote(s) => s.address(),
+            Owner::Pkcs11(s) => s.address(),
+            Owner::Node(a) => *a,
+        }
+    }
+}
+
+impl From<LocalWallet> for Owner {
+    fn from(wallet: LocalWallet) -> Self {
+        Owner::Wallet(wallet)
+    }
+}
+
+/// Options for the type, the fees and the gas limit of the transactions. The fees which aren't
+/// set are estimated through the Ethereum node.
+#[derive(Clone, Debug)]
+pub struct GasOptions {
+    pub tx_type: TxType,
+    /// The maximum fee per gas in wei of type-2 transactions.
+    pub max_fee_per_gas: Option<U256>,
+    /// The maximum priority fee per gas in wei of type-2 transactions.
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// The gas price in wei of legacy transactions.
+    pub gas_price: Option<U256>,
+    /// The multiplier applied to the estimated gas for setting the gas limit.
+    pub gas_limit_multiplier: f64,
+    /// The fee per gas in wei above which the transactions aren't sent.
+    pub fee_cap: Option<U256>,
+    /// The time that a transaction can be pending before replacing it with another one with
+    /// higher fees. The transactions aren't replaced when it's `None`.
+    pub pending_timeout: Option<Duration>,
+    /// The percentage by which the fees of a replaced transaction are increased.
+    pub fee_bump_percent: u64,
+    /// The maximum number of times that a pending transaction is replaced.
+    pub max_replacements: u32,
+}
+
+impl Default for GasOptions {
+    fn default() -> Self {
+        Self {
+            tx_type: TxType::Eip1559,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            gas_price: None,
+            gas_limit_multiplier: 1.0,
+            fee_cap: None,
+            pending_timeout: None,
+            fee_bump_percent: 20,
+            max_replacements: 3,
+        }
+    }
+}
+
+/// When a mined transaction is considered final, so it's very unlikely that a chain reorganization
+/// removes it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Finality {
+    /// The number of blocks mined since the block which contains the transaction, including it.
+    Confirmations(u64),
+    /// The block which contains the transaction is finalized (i.e. the `finalized` block tag).
+    Finalized,
+}
+
+impl Default for Finality {
+    fn default() -> Self {
+        Finality::Confirmations(1)
+    }
+}
+
+/// The origin of the external error returned when a mined transaction isn't in the canonical
+/// chain anymore because of a chain reorganization, so what it registered may not be registered.
+#[derive(Debug)]
+pub struct Reorg {
+    pub tx_hash: TxHash,
+    /// The number of the block where the transaction was mined.
+    pub block_number: u64,
+    /// The hash of the block where the transaction was mined.
+    pub block_hash: H256,
+}
+
+impl std::fmt::Display for Reorg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the transaction {:?} mined in the block {} ({:?}) was removed from the canonical chain by a chain reorganization",
+            self.tx_hash, self.block_number, self.block_hash
+        )
+    }
+}
+
+impl std::error::Error for Reorg {}
+
+/// The origin of the external error returned when a transaction is dropped from the mempool of
+/// the Ethereum node without being mined, so what it registers isn't registered.
+#[derive(Debug)]
+pub struct Dropped {
+    pub tx_hash: TxHash,
+}
+
+impl std::fmt::Display for Dropped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the transaction {:?} was dropped from the mempool without being mined",
+            self.tx_hash
+        )
+    }
+}
+
+impl std::error::Error for Dropped {}
+
+/// The origin of the external error returned when a transaction is still pending after replacing
+/// it the maximum number of times. The last replacement may still be mined.
+#[derive(Debug)]
+pub struct Stuck {
+    /// The hash of the last replacement.
+    pub tx_hash: TxHash,
+    pub replacements: u32,
+}
+
+impl std::fmt::Display for Stuck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the transaction {:?} is still pending after replacing it {} times with higher fees",
+            self.tx_hash, self.replacements
+        )
+    }
+}
+
+impl std::error::Error for Stuck {}
+
+/// The origin of the external error returned when a transaction is mined but it failed (i.e.
+/// reverted), so what it registers isn't registered.
+#[derive(Debug)]
+pub struct Reverted {
+    pub tx_hash: TxHash,
+}
+
+impl std::fmt::Display for Reverted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the transaction {:?} was mined but reverted",
+            self.tx_hash
+        )
+    }
+}
+
+impl std::error::Error for Reverted {}
+
+/// Kinds of replacements of a pending transaction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Replacement {
+    /// The same transaction with higher fees.
+    SpeedUp,
+    /// A transfer of 0 ether to the sender itself with higher fees, so the pending transaction
+    /// isn't mined.
+    Cancel,
+}
+
+/// Types of transactions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TxType {
+    /// Transactions with a gas price, for the chains which don't support EIP-1559.
+    Legacy,
+    /// EIP-1559 (i.e. type-2) transactions.
+    Eip1559,
+}
+
+/// The fees of a transaction in wei.
+enum Fees {
+    Legacy {
+        gas_price: U256,
+    },
+    Eip1559 {
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+impl Fees {
+    /// Returns the maximum fee per gas that the transaction can pay.
+    fn fee_per_gas(&self) -> U256 {
+        match self {
+            Fees::Legacy { gas_price } => *gas_price,
+            Fees::Eip1559 {
+                max_fee_per_gas, ..
+            } => *max_fee_per_gas,
+        }
+    }
+}
+
+/// Status of a transaction.
+pub struct TxStatus {
+    pub state: TxState,
+    /// The address that sent the transaction.
+    pub from: Address,
+    pub nonce: u64,
+    /// The number of the block which contains the transaction; `None` while it's pending.
+    pub block_number: Option<u64>,
+    /// The number of blocks mined since the block which contains the transaction, including it.
+    pub confirmations: u64,
+    /// The registered CID when the transaction is a call to the register method of the contract.
+    pub cid: Option<String>,
+}
+
+/// States of a transaction.
+#[derive(Debug, PartialEq)]
+pub enum TxState {
+    /// The transaction isn't mined yet.
+    Pending,
+    /// The transaction is mined and it succeeded.
+    Mined,
+    /// The transaction is mined but it failed (i.e. reverted).
+    Failed,
+}
+
+// Send the register `call` and return the hash of the transaction without waiting until it's
+// mined.
+async fn send_register<M: Middleware + 'static>(
+    call: ContractCall<M, ()>,
+) -> Result<TxHash, Error> {
+    let pending = call
+        .send()
+        .await
+        .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Ethereum))?;
+
+    Ok(pending.tx_hash())
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::*;
 
     use std::env;
 
-    #[test_with::http(localhost:8545)]
-    #[test_with::env(IPFS_CIDS_OWNERS_CONTRACT_ADDRESS, IPFS_CIDS_OWNER_PRIV_KEY)]
+    use mktemp::Temp;
+
     #[tokio::test]
-    async fn test_cids_owners_register() {
-        let contract_addr = env::var("IPFS_CIDS_OWNERS_CONTRACT_ADDRESS").expect(
-            "BUG this test should be ignored without the 'IPFS_CIDS_OWNERS_CONTRACT_ADDRESS' env var",
+    async fn test_retry_http() {
+        // Nothing listens on the port.
+        let provider = Provider::new(RetryHttp {
+            http: Http::from_str("http://localhost:1").expect("a valid URL"),
+            policy: RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            },
+        });
+
+        let err = provider
+            .get_block_number()
+            .await
+            .expect_err("connection refused");
+        assert!(
+            err.to_string().contains("after 2 attempts"),
+            "connection errors are retried: {}",
+            err
         );
-        let contract_addr = contract_addr
-            .parse::<Address>()
-            .expect("a valid contract address");
 
-        let owner_priv_key = env::var("IPFS_CIDS_OWNER_PRIV_KEY").expect(
-            "BUG this test should be ignored without the 'IPFS_CIDS_OWNER_PRIV_KEY' env var",
+        let rpc_error = |code: i64, message: &str| {
+            HttpClientError::JsonRpcError(
+                serde_json::from_value(serde_json::json!({ "code": code, "message": message }))
+                    .expect("a valid JSON-RPC error"),
+            )
+        };
+        assert!(
+            transient_rpc_error(Some(&rpc_error(429, "too many requests")), true),
+            "rate limit"
+        );
+        assert!(
+            !transient_rpc_error(Some(&rpc_error(-32000, "nonce too low")), false),
+            "node error"
+        );
+        assert!(transient_rpc_error(None, false), "timed out call");
+        assert!(
+            !transient_rpc_error(None, true),
+            "timed out transaction, which may have been sent"
         );
-        let owner_wallet = owner_priv_key
+    }
+
+    /// Returns the contract address and the owner's wallet of the environment variables which the
+    /// tests that register CIDs require.
+    fn test_env() -> (Address, LocalWallet) {
+        let contract_addr = env::var("IPFS_CIDS_OWNERS_CONTRACT_ADDRESS")
+            .expect(
+                "BUG this test should be ignored without the 'IPFS_CIDS_OWNERS_CONTRACT_ADDRESS' \
+                 env var",
+            )
+            .parse::<Address>()
+            .expect("a valid contract address");
+        let owner_wallet = env::var("IPFS_CIDS_OWNER_PRIV_KEY")
+            .expect(
+                "BUG this test should be ignored without the 'IPFS_CIDS_OWNER_PRIV_KEY' env var",
+            )
             .parse::<LocalWallet>()
             .expect("a valid private key. NOTE set it without the '0x' prefix)");
 
-        let cids_owners =
-            CIDsOwners::new(contract_addr, "http://localhost:8545", Some(1337 as u64))
-                .expect("instance CIDsOwners successfully");
+        (contract_addr, owner_wallet)
+    }
+
+    /// Starts a registration session of `owner` whose lock is in a new temporary directory, which
+    /// is returned for keeping it while the session is used.
+    pub(crate) fn test_session(cids_owners: &CIDsOwners, owner: Owner) -> (Session, Temp) {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let lock = OwnerLock::acquire_in(&dir, owner.address()).expect("acquire the owner lock");
+        (cids_owners.session(owner, lock), dir)
+    }
+
+    #[test_with::http(localhost:8545)]
+    #[test_with::env(IPFS_CIDS_OWNERS_CONTRACT_ADDRESS, IPFS_CIDS_OWNER_PRIV_KEY)]
+    #[tokio::test]
+    async fn test_cids_owners_register() {
+        let (contract_addr, owner_wallet) = test_env();
+
+        let cids_owners = CIDsOwners::new(
+            contract_addr,
+            "http://localhost:8545",
+            Some(1337_u64),
+            RetryPolicy::default(),
+        )
+        .expect("instance CIDsOwners successfully");
+        let (session, _dir) = test_session(&cids_owners, owner_wallet.clone().into());
 
-        cids_owners
-            .register_cid_owner("fake CID", owner_wallet.clone())
+        session
+            .register("fake CID")
             .await
             .expect("register a CID successfully");
 
         let registered_cids = cids_owners
-            .my_registered_cids(owner_wallet)
+            .registered_cids(owner_wallet.address())
+            .await
+            .expect("get registered CIDs successful");
+
+        let tx_hash = session
+            .send_register("fake CID no wait")
             .await
-            .expect("get my registered CIDs successful");
+            .expect("send the register transaction successfully");
+        let status = cids_owners
+            .transaction_status(tx_hash)
+            .await
+            .expect("get the transaction status successfully");
+        assert_eq!(
+            status.cid.as_deref(),
+            Some("fake CID no wait"),
+            "decoded CID"
+        );
+        assert_eq!(status.from, owner_wallet.address(), "transaction sender");
+
+        let cids = [String::from("fake CID 1"), String::from("fake CID 2")];
+        let mut nonces = Vec::new();
+        for res in session.register_cids(&cids).await {
+            let receipt = res.expect("register the CIDs successfully");
+            let status = cids_owners
+                .transaction_status(receipt.transaction_hash)
+                .await
+                .expect("get the transaction status successfully");
+            nonces.push(status.nonce);
+        }
+        assert_eq!(nonces[1], nonces[0] + 1, "consecutive nonces");
+        assert_eq!(
+            nonces[0],
+            status.nonce + 1,
+            "nonce after the pending transaction"
+        );
 
         // Note we check that at least the CID is once because if we run the test several times the
         // CID will be more than once and checking that the vector only contains one element and
@@ -135,4 +1309,123 @@ mod test {
             "has the registered CID"
         );
     }
+
+    #[test_with::http(localhost:8545)]
+    #[test_with::env(IPFS_CIDS_OWNERS_CONTRACT_ADDRESS, IPFS_CIDS_OWNER_PRIV_KEY)]
+    #[tokio::test]
+    async fn test_session_find_register() {
+        let (contract_addr, owner_wallet) = test_env();
+
+        let cids_owners = CIDsOwners::new(
+            contract_addr,
+            "http://localhost:8545",
+            Some(1337_u64),
+            RetryPolicy::default(),
+        )
+        .expect("instance CIDsOwners successfully");
+        let (session, _dir) = test_session(&cids_owners, owner_wallet.into());
+
+        let mut sent = None;
+        let hash = session
+            .send_register_with_nonce("fake CID resumed", |n, b| {
+                sent = Some((n, b));
+                Ok(())
+            })
+            .await
+            .expect("send the register transaction successfully");
+        let (nonce, block) = sent.expect("the nonce is reported");
+        session.wait(hash).await.expect("the transaction is mined");
+
+        assert_eq!(
+            session
+                .find_register("fake CID resumed", nonce, block, Some(hash))
+                .await
+                .expect("find the transaction"),
+            Some(hash),
+            "known transaction"
+        );
+        assert_eq!(
+            session
+                .find_register("fake CID resumed", nonce, block, None)
+                .await
+                .expect("find the transaction"),
+            Some(hash),
+            "transaction without recorded hash"
+        );
+        assert_eq!(
+            session
+                .find_register("fake CID other", nonce, block, Some(TxHash::random()))
+                .await
+                .expect("find the transaction"),
+            None,
+            "the nonce is used by another registration"
+        );
+        assert_eq!(
+            session
+                .find_register("fake CID resumed", nonce + 1000, block, None)
+                .await
+                .expect("find the transaction"),
+            None,
+            "unused nonce"
+        );
+
+        assert!(
+            matches!(
+                session
+                    .send_register_with_nonce("fake CID not sent", |_, _| Err(
+                        Error::invalid_arguments("nonce", "not recorded")
+                    ))
+                    .await,
+                Err(Error::InvalidArguments(_))
+            ),
+            "the transaction isn't sent when the nonce isn't recorded"
+        );
+    }
+
+    #[test_with::http(localhost:8545)]
+    #[test_with::env(IPFS_CIDS_OWNERS_CONTRACT_ADDRESS, IPFS_CIDS_OWNER_PRIV_KEY)]
+    #[tokio::test]
+    async fn test_cids_owners_gas_options() {
+        let (contract_addr, owner_wallet) = test_env();
+
+        let gas_price = U256::from(2_000_000_000_u64);
+        let cids_owners = CIDsOwners::new(
+            contract_addr,
+            "http://localhost:8545",
+            Some(1337_u64),
+            RetryPolicy::default(),
+        )
+        .expect("instance CIDsOwners successfully")
+        .with_gas_options(GasOptions {
+            tx_type: TxType::Legacy,
+            gas_price: Some(gas_price),
+            gas_limit_multiplier: 1.5,
+            ..GasOptions::default()
+        });
+
+        let (session, _dir) = test_session(&cids_owners, owner_wallet.clone().into());
+        let receipt = session
+            .register("fake CID legacy")
+            .await
+            .expect("register a CID with a legacy transaction successfully");
+        assert_eq!(
+            receipt.effective_gas_price,
+            Some(gas_price),
+            "gas price of the transaction"
+        );
+
+        let cids_owners = cids_owners.with_gas_options(GasOptions {
+            max_fee_per_gas: Some(gas_price),
+            fee_cap: Some(gas_price - 1),
+            ..GasOptions::default()
+        });
+        let (session, _dir) = test_session(&cids_owners, owner_wallet.into());
+        assert!(
+            matches!(
+                session.register("fake CID fee cap").await,
+                Err(Error::InvalidArguments(_))
+            ),
+            "fee per gas exceeds the fee cap"
+        );
+    }
 }
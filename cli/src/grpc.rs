@@ -0,0 +1,576 @@
+//! gRPC server which implements the service defined by the `ipfs-cids-owners-grpc` crate on top of
+//! the IPFS and Ethereum high level APIs of this crate.
+//!
+//! The uploads are streamed to IPFS as the chunks arrive, without keeping the whole file in
+//! memory, and the registrations stream back an event when the transaction is sent and another
+//! when it's mined.
+
+use crate::{
+    cmd,
+    error::{BoxError, Error},
+    ethereum, ipfs,
+    retry::RetryPolicy,
+    server,
+};
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use ethers::{
+    abi::AbiEncode,
+    core::types::{Address, TxHash},
+};
+use futures::{SinkExt, TryStreamExt};
+use ipfs_api_backend_hyper::IpfsClient;
+use ipfs_cids_owners_grpc::cids_owners::{
+    cids_owners_server::{CidsOwners, CidsOwnersServer},
+    registration_event::Event,
+    tx_status_response, upload_request, ListCidsRequest, ListCidsResponse, RegisterRequest,
+    RegistrationEvent, RegistrationFailure, Transaction, TxStatusRequest, TxStatusResponse,
+    UploadRequest, UploadResponse,
+};
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status, Streaming};
+
+/// Number of chunks of an upload and registration events which are buffered before applying
+/// back-pressure.
+const STREAM_BUFFER: usize = 16;
+
+/// The state shared by all the requests.
+pub struct State {
+    pub ipfs: IpfsClient,
+    pub cids_owners: ethereum::CIDsOwners,
+    /// The registration session of the owner of the CIDs registered through the server.
+    pub session: ethereum::Session,
+    /// The retry policy of the calls to IPFS.
+    pub retry: RetryPolicy,
+    /// The token that the requests must send. No authentication is required when it's `None`.
+    pub auth_token: Option<String>,
+}
+
+/// Runs the server listening on `addr` until it fails.
+pub async fn run(state: State, addr: SocketAddr) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr).await.map_err(|err| {
+        Error::invalid_arguments("addr", &format!("cannot listen on '{}'. {}", addr, err))
+    })?;
+
+    serve(state, listener).await
+}
+
+/// Runs the server accepting the connections of `listener` until it fails.
+// tonic interceptors must return a `Status` as error, so it cannot be boxed.
+#[allow(clippy::result_large_err)]
+pub async fn serve(state: State, listener: TcpListener) -> Result<(), Error> {
+    let auth_token = state.auth_token.clone();
+    let service = CidsOwnersServer::with_interceptor(
+        Service {
+            state: Arc::new(state),
+        },
+        move |req| authenticate(auth_token.as_deref(), req),
+    );
+
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+        .map_err(|err| Error::internal("gRPC server failure", BoxError::from(err)))
+}
+
+/// Rejects the requests which don't send `token` as a bearer token, if it's set.
+#[allow(clippy::result_large_err)]
+fn authenticate(token: Option<&str>, req: Request<()>) -> Result<Request<()>, Status> {
+    let token = match token {
+        Some(t) => t,
+        None => return Ok(req),
+    };
+
+    let authorized = req
+        .metadata()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| server::constant_time_eq(t.as_bytes(), token.as_bytes()));
+
+    if authorized {
+        Ok(req)
+    } else {
+        Err(Status::unauthenticated(
+            "missing or invalid authentication token",
+        ))
+    }
+}
+
+/// Implementation of the CidsOwners gRPC service.
+struct Service {
+    state: Arc<State>,
+}
+
+type EventStream = ReceiverStream<Result<RegistrationEvent, Status>>;
+
+#[tonic::async_trait]
+impl CidsOwners for Service {
+    async fn upload(
+        &self,
+        req: Request<Streaming<UploadRequest>>,
+    ) -> Result<Response<UploadResponse>, Status> {
+        let uploaded = upload(&self.state, req.into_inner()).await?;
+        Ok(Response::new(UploadResponse {
+            cid: uploaded.cid,
+            size: uploaded.size,
+        }))
+    }
+
+    type UploadAndRegisterStream = EventStream;
+
+    async fn upload_and_register(
+        &self,
+        req: Request<Streaming<UploadRequest>>,
+    ) -> Result<Response<Self::UploadAndRegisterStream>, Status> {
+        let state = self.state.clone();
+        let (events, rx) = mpsc::channel(STREAM_BUFFER);
+
+        tokio::spawn(async move {
+            let uploaded = match upload(&state, req.into_inner()).await {
+                Ok(u) => u,
+                Err(status) => {
+                    let _ = events.send(Err(status)).await;
+                    return;
+                }
+            };
+
+            let cid = uploaded.cid.clone();
+            let uploaded = Event::Uploaded(UploadResponse {
+                cid: uploaded.cid,
+                size: uploaded.size,
+            });
+            if send_event(&events, uploaded).await {
+                register(&state, cid, &events).await;
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    type RegisterStream = EventStream;
+
+    async fn register(
+        &self,
+        req: Request<RegisterRequest>,
+    ) -> Result<Response<Self::RegisterStream>, Status> {
+        let cids = req.into_inner().cids;
+        for (i, c) in cids.iter().enumerate() {
+            if let Err(err) = cid::Cid::try_from(c.as_str()) {
+                return Err(to_status(Error::invalid_arguments(
+                    &format!("cids[{}]", i),
+                    &format!("invalid CID format. {}", err),
+                )));
+            }
+        }
+
+        let state = self.state.clone();
+        let (events, rx) = mpsc::channel(STREAM_BUFFER);
+
+        tokio::spawn(async move {
+            for cid in cids {
+                if !register(&state, cid, &events).await {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn list_cids(
+        &self,
+        req: Request<ListCidsRequest>,
+    ) -> Result<Response<ListCidsResponse>, Status> {
+        let req = req.into_inner();
+        let owner = req.owner.parse::<Address>().map_err(|err| {
+            to_status(Error::invalid_arguments(
+                "owner",
+                &format!("invalid format for Ethereum address. {}", err),
+            ))
+        })?;
+
+        let cids = self
+            .state
+            .cids_owners
+            .registered_cids(owner)
+            .await
+            .map_err(to_status)?;
+        let total = cids.len() as u64;
+
+        Ok(Response::new(ListCidsResponse {
+            owner: req.owner,
+            total,
+            offset: req.offset,
+            cids: cmd::paginate(cids, req.offset as usize, req.limit.map(|l| l as usize)),
+        }))
+    }
+
+    async fn tx_status(
+        &self,
+        req: Request<TxStatusRequest>,
+    ) -> Result<Response<TxStatusResponse>, Status> {
+        let req = req.into_inner();
+        let hash = req.hash.parse::<TxHash>().map_err(|err| {
+            to_status(Error::invalid_arguments(
+                "hash",
+                &format!("invalid format for Ethereum transaction hash. {}", err),
+            ))
+        })?;
+
+        let status = self
+            .state
+            .cids_owners
+            .transaction_status(hash)
+            .await
+            .map_err(to_status)?;
+
+        let state = match status.state {
+            ethereum::TxState::Pending => tx_status_response::State::Pending,
+            ethereum::TxState::Mined => tx_status_response::State::Mined,
+            ethereum::TxState::Failed => tx_status_response::State::Failed,
+        };
+
+        Ok(Response::new(TxStatusResponse {
+            hash: hash.encode_hex(),
+            state: state.into(),
+            from: format!("{:?}", status.from),
+            nonce: status.nonce,
+            block_number: status.block_number,
+            confirmations: status.confirmations,
+            cid: status.cid,
+        }))
+    }
+}
+
+/// Uploads the file of the `stream` of requests to IPFS.
+/// The chunks are forwarded to the IPFS client through a channel because the gRPC stream cannot be
+/// shared between threads, which the IPFS client requires.
+async fn upload(
+    state: &State,
+    mut stream: Streaming<UploadRequest>,
+) -> Result<ipfs::UploadedFile, Status> {
+    use std::io::Error as ioError;
+
+    let remote_path = match stream.message().await?.and_then(|m| m.content) {
+        Some(upload_request::Content::Metadata(m)) => m.remote_path,
+        _ => {
+            return Err(to_status(Error::invalid_arguments(
+                "stream",
+                "the first message must be the upload metadata",
+            )))
+        }
+    };
+
+    let (mut chunks, rx) = futures::channel::mpsc::channel(STREAM_BUFFER);
+    let forward = tokio::spawn(async move {
+        loop {
+            let status = match stream.message().await {
+                Ok(None) => return Ok(()),
+                Ok(Some(UploadRequest {
+                    content: Some(upload_request::Content::Chunk(c)),
+                })) => {
+                    if chunks.send(Ok(c)).await.is_err() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Ok(Some(UploadRequest {
+                    content: Some(upload_request::Content::Abort(reason)),
+                })) => Status::aborted(format!("the client aborted the upload. {}", reason)),
+                Ok(Some(_)) => to_status(Error::invalid_arguments(
+                    "stream",
+                    "only the first message of the stream can be the upload metadata",
+                )),
+                Err(status) => status,
+            };
+
+            // Fail the upload rather than ending the content, which would upload it truncated.
+            let _ = chunks
+                .send(Err(ioError::other(status.message().to_string())))
+                .await;
+            return Err(status);
+        }
+    });
+
+    let uploaded = ipfs::Client::with_client(&state.ipfs, state.retry)
+        .upload_data(rx.into_async_read(), remote_path.as_deref())
+        .await;
+
+    // The failure of the stream is the cause of the failed upload, so it's the one reported.
+    match forward.await {
+        Ok(Ok(())) => uploaded.map_err(to_status),
+        Ok(Err(status)) => Err(status),
+        Err(err) => Err(to_status(Error::internal(
+            "the task which forwards the uploaded content failed",
+            BoxError::from(err),
+        ))),
+    }
+}
+
+/// Registers `cid` and sends the events of the registration to `events`.
+/// It returns `false` when the client has gone, hence no more events should be sent.
+async fn register(
+    state: &State,
+    cid: String,
+    events: &mpsc::Sender<Result<RegistrationEvent, Status>>,
+) -> bool {
+    let failed = |tx_hash, err: Error| {
+        Event::Failed(RegistrationFailure {
+            cid: cid.clone(),
+            tx_hash,
+            error: err.to_string(),
+        })
+    };
+
+    let hash = match state.session.send_register(&cid).await {
+        Ok(h) => h,
+        Err(err) => return send_event(events, failed(None, err)).await,
+    };
+
+    let tx_hash = hash.encode_hex();
+    let sent = Event::Sent(Transaction {
+        cid: cid.clone(),
+        tx_hash: tx_hash.clone(),
+    });
+    if !send_event(events, sent).await {
+        return false;
+    }
+
+    // The mined transaction may be a replacement of the sent one.
+    let event = match state.session.wait(hash).await {
+        Ok(receipt) => Event::Mined(Transaction {
+            cid: cid.clone(),
+            tx_hash: receipt.transaction_hash.encode_hex(),
+        }),
+        Err(err) => failed(Some(tx_hash), err),
+    };
+
+    send_event(events, event).await
+}
+
+/// Sends `event` to `events` and returns `false` if the client has gone.
+async fn send_event(
+    events: &mpsc::Sender<Result<RegistrationEvent, Status>>,
+    event: Event,
+) -> bool {
+    events
+        .send(Ok(RegistrationEvent { event: Some(event) }))
+        .await
+        .is_ok()
+}
+
+/// Converts `err` to the gRPC status corresponding to its kind.
+fn to_status(err: Error) -> Status {
+    match err {
+        Error::InvalidArguments(_) => Status::invalid_argument(err.to_string()),
+        Error::External(_) => Status::unavailable(err.to_string()),
+        Error::Internal(_) => Status::internal(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+
+    use ethers::signers::LocalWallet;
+    use futures::StreamExt;
+    use ipfs_cids_owners_grpc::cids_owners::cids_owners_client::CidsOwnersClient;
+    use mktemp::Temp;
+    use tonic::{transport::Channel, Code};
+
+    /// Starts a server in-process on a random local port and returns a client connected to it.
+    async fn start(state: State) -> CidsOwnersClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind a local port");
+        let addr = listener.local_addr().expect("listener address");
+
+        tokio::spawn(serve(state, listener));
+
+        CidsOwnersClient::connect(format!("http://{}", addr))
+            .await
+            .expect("connect to the server")
+    }
+
+    /// Returns the state of the server and the temporary directory of the owner's lock, which must
+    /// be kept while the server runs.
+    fn test_state(owner: LocalWallet, auth_token: Option<&str>) -> (State, Temp) {
+        let contract_addr = env::var("IPFS_CIDS_OWNERS_CONTRACT_ADDRESS")
+            .ok()
+            .and_then(|a| a.parse::<Address>().ok())
+            .unwrap_or_default();
+
+        let cids_owners = ethereum::CIDsOwners::new(
+            contract_addr,
+            "http://localhost:8545",
+            Some(1337_u64),
+            RetryPolicy::default(),
+        )
+        .expect("create CIDsOwners");
+        let (session, dir) = ethereum::test::test_session(&cids_owners, owner.into());
+
+        let state = State {
+            ipfs: IpfsClient::default(),
+            session,
+            cids_owners,
+            retry: RetryPolicy::default(),
+            auth_token: auth_token.map(String::from),
+        };
+        (state, dir)
+    }
+
+    fn random_wallet() -> LocalWallet {
+        LocalWallet::new(&mut ethers::core::rand::thread_rng())
+    }
+
+    #[tokio::test]
+    async fn test_authentication() {
+        let (state, _dir) = test_state(random_wallet(), Some("secret"));
+        let mut client = start(state).await;
+
+        let req = || ListCidsRequest {
+            owner: String::from("invalid"),
+            offset: 0,
+            limit: None,
+        };
+
+        let status = client.list_cids(req()).await.expect_err("missing token");
+        assert_eq!(status.code(), Code::Unauthenticated, "missing token");
+
+        let mut authorized = Request::new(req());
+        authorized
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        let status = client.list_cids(authorized).await.expect_err("valid token");
+        assert_eq!(status.code(), Code::InvalidArgument, "valid token");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        let (state, _dir) = test_state(random_wallet(), None);
+        let mut client = start(state).await;
+
+        let status = client
+            .tx_status(TxStatusRequest {
+                hash: String::from("0x01"),
+            })
+            .await
+            .expect_err("invalid hash");
+        assert_eq!(status.code(), Code::InvalidArgument, "invalid hash");
+
+        let status = client
+            .register(RegisterRequest {
+                cids: vec![String::from("not a CID")],
+            })
+            .await
+            .expect_err("invalid CID");
+        assert_eq!(status.code(), Code::InvalidArgument, "invalid CID");
+
+        let status = client
+            .upload(futures::stream::iter([UploadRequest {
+                content: Some(upload_request::Content::Chunk(vec![1, 2, 3])),
+            }]))
+            .await
+            .expect_err("missing metadata");
+        assert_eq!(status.code(), Code::InvalidArgument, "missing metadata");
+    }
+
+    #[tokio::test]
+    async fn test_upload_abort() {
+        /// Reader which always fails, like a file on a failing disk.
+        struct FailingReader;
+
+        impl futures::io::AsyncRead for FailingReader {
+            fn poll_read(
+                self: std::pin::Pin<&mut Self>,
+                _: &mut std::task::Context<'_>,
+                _: &mut [u8],
+            ) -> std::task::Poll<std::io::Result<usize>> {
+                std::task::Poll::Ready(Err(std::io::Error::other("disk failure")))
+            }
+        }
+
+        let (state, _dir) = test_state(random_wallet(), None);
+        let mut client = start(state).await;
+
+        let data = futures::io::AsyncReadExt::chain(
+            futures::io::Cursor::new(b"partial content".to_vec()),
+            FailingReader,
+        );
+        let requests = ipfs_cids_owners_grpc::abort_on_error(
+            ipfs_cids_owners_grpc::upload_requests(data, None),
+        );
+
+        let status = client.upload(requests).await.expect_err("aborted upload");
+        assert_eq!(status.code(), Code::Aborted, "aborted upload");
+        assert!(
+            status.message().contains("disk failure"),
+            "the reason is the read error: {}",
+            status.message()
+        );
+    }
+
+    #[test_with::http(localhost:5001)]
+    #[test_with::http(localhost:8545)]
+    #[test_with::env(IPFS_CIDS_OWNERS_CONTRACT_ADDRESS, IPFS_CIDS_OWNER_PRIV_KEY)]
+    #[tokio::test]
+    async fn test_upload_and_register() {
+        let owner = env::var("IPFS_CIDS_OWNER_PRIV_KEY")
+            .expect(
+                "BUG this test should be ignored without the 'IPFS_CIDS_OWNER_PRIV_KEY' env var",
+            )
+            .parse::<LocalWallet>()
+            .expect("a valid private key. NOTE set it without the '0x' prefix)");
+        let owner_address = format!("{:?}", ethers::signers::Signer::address(&owner));
+
+        let (state, _dir) = test_state(owner, None);
+        let mut client = start(state).await;
+
+        // Make the content unique for not registering the same CID every time that the test runs.
+        let content = format!(
+            "gRPC upload and register {:?}",
+            std::time::SystemTime::now()
+        );
+        let requests =
+            ipfs_cids_owners_grpc::abort_on_error(ipfs_cids_owners_grpc::upload_requests(
+                futures::io::Cursor::new(content.into_bytes()),
+                None,
+            ));
+
+        let events: Vec<Event> = client
+            .upload_and_register(requests)
+            .await
+            .expect("upload and register successfully")
+            .into_inner()
+            .map(|e| e.expect("registration event").event.expect("event is set"))
+            .collect()
+            .await;
+
+        let cid = match events.as_slice() {
+            [Event::Uploaded(u), Event::Sent(s), Event::Mined(m)] => {
+                assert_eq!(u.cid, s.cid, "sent CID");
+                assert_eq!(s, m, "mined transaction");
+                u.cid.clone()
+            }
+            _ => panic!("unexpected events: {:?}", events),
+        };
+
+        let list = client
+            .list_cids(ListCidsRequest {
+                owner: owner_address,
+                offset: 0,
+                limit: None,
+            })
+            .await
+            .expect("list CIDs successfully")
+            .into_inner();
+        assert!(list.cids.contains(&cid), "has the registered CID");
+    }
+}
@@ -1,85 +1,809 @@
 //! IPFS high level API to fulfill the requirements of the command-line application.
 
-use crate::error::{BoxError, Error, ExternalSystem};
+use crate::{
+    error::{BoxError, Error, ExternalSystem},
+    retry::{RetryError, RetryPolicy},
+};
 use std::io::ErrorKind as ioErrorKind;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use async_fs::File;
-use ipfs_api_backend_hyper::{request, IpfsApi, IpfsClient};
+use futures::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    TryStreamExt,
+};
+use ipfs_api_backend_hyper::{request, response, Error as IpfsError, Form, IpfsApi, IpfsClient};
+
+/// Name of the files which contain rules, with the same format than `.gitignore` files, of the
+/// files to exclude when uploading a directory.
+pub const IGNORE_FILENAME: &str = ".ipfsignore";
 
 /// IPFS client wrapper to expose higher level operations.
+/// The calls to IPFS are retried and timed out with the retry policy of the client, except the
+/// uploads, which aren't timed out because their duration depends on the size of the content.
 pub struct Client<'a> {
     client: &'a IpfsClient,
+    retry: RetryPolicy,
 }
 
 impl<'a> Client<'a> {
-    /// Creates a client that uses the passed IPFS client.
-    pub fn with_client(client: &'a IpfsClient) -> Self {
-        Client { client }
+    /// Creates a client that uses the passed IPFS client and retries its calls with `retry`.
+    pub fn with_client(client: &'a IpfsClient, retry: RetryPolicy) -> Self {
+        Client { client, retry }
     }
 
     /// Uploads a file to IPFS with optional specifying the remote path and returns its
-    /// corresponding CID.
+    /// corresponding CID and the size reported by IPFS.
     pub async fn uploload_file(
         &self,
         filepath: &Path,
         remote_path: Option<&str>,
-    ) -> Result<String, Error> {
-        let add_opts = if let Some(p) = remote_path {
-            if !p.starts_with("/") {
-                return Err(Error::invalid_arguments(
-                    "remote_path",
-                    "must begin with slack ('/')",
-                ));
-            }
-            request::Add {
-                to_files: remote_path,
-                ..Default::default()
-            }
-        } else {
-            Default::default()
+    ) -> Result<UploadedFile, Error> {
+        validate_remote_path("remote_path", remote_path)?;
+        self.add_file(filepath, || upload_options(remote_path))
+            .await
+    }
+
+    /// Uploads the content read from `data` to IPFS with optional specifying the remote path and
+    /// returns its corresponding CID and the size reported by IPFS.
+    /// The CID is the same that [`Self::uploload_file`] returns for a file with the same content.
+    /// It isn't retried because `data` is consumed by the first attempt.
+    pub async fn upload_data<R>(
+        &self,
+        data: R,
+        remote_path: Option<&str>,
+    ) -> Result<UploadedFile, Error>
+    where
+        R: 'static + AsyncRead + Send + Sync + Unpin,
+    {
+        validate_remote_path("remote_path", remote_path)?;
+
+        let policy = RetryPolicy {
+            max_attempts: 1,
+            ..self.upload_policy()
         };
+        let mut data = Some(data);
+        policy
+            .retry(
+                || {
+                    let data = data.take().expect("BUG data is only read by one attempt");
+                    self.add(data, upload_options(remote_path))
+                },
+                transient_error,
+            )
+            .await
+            .map_err(retry_error)
+    }
 
-        let file = File::open(filepath).await.map_err(|err| match err.kind() {
-            ioErrorKind::NotFound => Error::invalid_arguments("filepath", "file not found"),
-            ioErrorKind::PermissionDenied => {
-                Error::invalid_arguments("filepath", "not read permissions to the file")
-            }
-            _ => Error::internal("system error when reading the file", BoxError::from(err)),
+    /// Computes the CID of a file without storing it in IPFS.
+    /// The CID is computed with the same options used by [`Self::uploload_file`], so it's the
+    /// CID that the file gets when it's uploaded.
+    pub async fn file_cid(&self, filepath: &Path) -> Result<String, Error> {
+        let added = self
+            .add_file(filepath, || request::Add {
+                only_hash: Some(true),
+                ..Default::default()
+            })
+            .await?;
+        Ok(added.cid)
+    }
+
+    /// Downloads the content of `cid` and writes it into `writer` as it's received.
+    /// It returns the number of written bytes.
+    /// Only the request until the first chunk of content is received is retried and timed out,
+    /// because the content written into `writer` cannot be written again.
+    pub async fn download<W>(&self, cid: &str, writer: &mut W) -> Result<u64, Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let (mut stream, mut next) = self
+            .retry
+            .retry(
+                || async {
+                    let mut stream = self.client.cat(cid);
+                    let first = stream.try_next().await.map_err(external_error)?;
+                    Ok((stream, first))
+                },
+                transient_error,
+            )
+            .await
+            .map_err(retry_error)?;
+
+        let mut size = 0;
+        while let Some(chunk) = next {
+            writer.write_all(&chunk).await.map_err(|err| {
+                Error::internal(
+                    "system error when writing the downloaded content",
+                    BoxError::from(err),
+                )
+            })?;
+            size += chunk.len() as u64;
+            next = stream.try_next().await.map_err(external_error)?;
+        }
+
+        writer.flush().await.map_err(|err| {
+            Error::internal(
+                "system error when writing the downloaded content",
+                BoxError::from(err),
+            )
         })?;
 
+        Ok(size)
+    }
+
+    /// Pins recursively `cid`.
+    pub async fn pin(&self, cid: &str) -> Result<(), Error> {
+        self.retry
+            .retry(
+                || async { self.client.pin_add(cid, true).await.map_err(external_error) },
+                transient_error,
+            )
+            .await
+            .map_err(retry_error)?;
+
+        Ok(())
+    }
+
+    /// Unpins recursively `cid`.
+    pub async fn unpin(&self, cid: &str) -> Result<(), Error> {
+        self.retry
+            .retry(
+                || async { self.client.pin_rm(cid, true).await.map_err(external_error) },
+                transient_error,
+            )
+            .await
+            .map_err(retry_error)?;
+
+        Ok(())
+    }
+
+    /// Returns the pinned CIDs, excluding the ones that are indirectly pinned.
+    pub async fn pins(&self) -> Result<Vec<Pin>, Error> {
+        let res = self
+            .retry
+            .retry(
+                || async { self.client.pin_ls(None, None).await.map_err(external_error) },
+                transient_error,
+            )
+            .await
+            .map_err(retry_error)?;
+
+        let mut pins: Vec<Pin> = res
+            .keys
+            .into_iter()
+            .filter(|(_, t)| t.typ != "indirect")
+            .map(|(cid, t)| Pin { cid, kind: t.typ })
+            .collect();
+        pins.sort_by(|a, b| a.cid.cmp(&b.cid));
+
+        Ok(pins)
+    }
+
+    /// Returns the retry policy of the uploads, which is the one of the client without timeouts,
+    /// because a timeout would cut off the uploads of large contents and upload them again.
+    fn upload_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            call_timeout: None,
+            timeout: None,
+            ..self.retry
+        }
+    }
+
+    /// Adds the file to IPFS using the options returned by `add_opts`. The file is opened again
+    /// for each attempt.
+    async fn add_file<'o>(
+        &self,
+        filepath: &Path,
+        add_opts: impl Fn() -> request::Add<'o>,
+    ) -> Result<UploadedFile, Error> {
+        self.upload_policy()
+            .retry(
+                || async {
+                    let file = File::open(filepath).await.map_err(|err| match err.kind() {
+                        ioErrorKind::NotFound => {
+                            Error::invalid_arguments("filepath", "file not found")
+                        }
+                        ioErrorKind::PermissionDenied => {
+                            Error::invalid_arguments("filepath", "not read permissions to the file")
+                        }
+                        _ => Error::internal(
+                            "system error when reading the file",
+                            BoxError::from(err),
+                        ),
+                    })?;
+
+                    self.add(file, add_opts()).await
+                },
+                transient_error,
+            )
+            .await
+            .map_err(retry_error)
+    }
+
+    /// Adds the content read from `data` to IPFS using `add_opts`.
+    async fn add<R>(&self, data: R, add_opts: request::Add<'_>) -> Result<UploadedFile, Error>
+    where
+        R: 'static + AsyncRead + Send + Sync + Unpin,
+    {
         let res = self
             .client
-            .add_async_with_options(file, add_opts)
+            .add_async_with_options(data, add_opts)
+            .await
+            .map_err(external_error)?;
+
+        let size = parse_size(&res)?;
+        Ok(UploadedFile {
+            cid: res.hash,
+            size,
+        })
+    }
+
+    /// Uploads recursively a directory to IPFS as a UnixFS directory and returns the CID of the
+    /// root directory and the CIDs of all its entries.
+    ///
+    /// The files which match the rules of the [`IGNORE_FILENAME`] files found in the directory
+    /// tree or that don't match `opts` globs aren't uploaded.
+    pub async fn upload_dir(
+        &self,
+        dirpath: &Path,
+        opts: &DirOptions<'_>,
+    ) -> Result<UploadedDir, Error> {
+        validate_remote_path("opts{remote_path}", opts.remote_path)?;
+
+        let add_opts = || {
+            if opts.only_hash {
+                request::Add {
+                    wrap_with_directory: Some(opts.wrap),
+                    only_hash: Some(true),
+                    ..Default::default()
+                }
+            } else {
+                request::Add {
+                    wrap_with_directory: Some(opts.wrap),
+                    to_files: opts.remote_path,
+                    ..Default::default()
+                }
+            }
+        };
+
+        let files = upload_dir_files(dirpath, opts)?;
+        // The root is the wrapping directory, which has no name, or the uploaded directory, whose
+        // name is the first component of the paths of the files in IPFS.
+        let root_name = if opts.wrap {
+            String::new()
+        } else {
+            Path::new(&files[0].1)
+                .components()
+                .next()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+
+        // The form is created again for each attempt because it's consumed by the request.
+        let mut res = self
+            .upload_policy()
+            .retry(
+                || async {
+                    let form = dir_form(&files);
+                    self.client
+                        .add_with_form(form, add_opts())
+                        .await
+                        .map_err(external_error)
+                },
+                transient_error,
+            )
             .await
-            .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::IPFS))?;
+            .map_err(retry_error)?;
+
+        let root = res
+            .iter()
+            .position(|r| r.name == root_name)
+            .map(|i| res.remove(i))
+            .ok_or_else(|| {
+                Error::external(
+                    BoxError::from("IPFS didn't report the CID of the uploaded directory"),
+                    ExternalSystem::IPFS,
+                )
+            })?;
+
+        let mut entries = Vec::with_capacity(res.len());
+        for r in res {
+            let size = parse_size(&r)?;
+            entries.push(UploadedEntry {
+                path: r.name,
+                cid: r.hash,
+                size,
+            });
+        }
+
+        let size = parse_size(&root)?;
+        Ok(UploadedDir {
+            root: UploadedFile {
+                cid: root.hash,
+                size,
+            },
+            entries,
+        })
+    }
+}
+
+/// Returns the options for uploading a file with the optional `remote_path`.
+fn upload_options(remote_path: Option<&str>) -> request::Add<'_> {
+    request::Add {
+        to_files: remote_path,
+        ..Default::default()
+    }
+}
 
-        Ok(res.hash)
+/// Validates that the optional `remote_path`, which is the argument `name`, is absolute.
+fn validate_remote_path(name: &str, remote_path: Option<&str>) -> Result<(), Error> {
+    match remote_path {
+        Some(p) if !p.starts_with('/') => Err(Error::invalid_arguments(
+            name,
+            "must begin with slack ('/')",
+        )),
+        _ => Ok(()),
     }
 }
 
+/// Wraps an error returned by IPFS.
+fn external_error(err: IpfsError) -> Error {
+    Error::external(BoxError::from(err), ExternalSystem::IPFS)
+}
+
+/// Returns true when the failure of an attempt of a call to IPFS is transient, so it's retried.
+/// `err` is `None` when the attempt timed out.
+/// The calls are idempotent, hence they are retried whatever the reason of the failure is: the
+/// connection failures, the connections closed before receiving the whole response and the I/O
+/// errors while receiving it.
+fn transient_error(err: Option<&Error>) -> bool {
+    let err = match err {
+        Some(Error::External(e)) => e,
+        Some(_) => return false,
+        None => return true,
+    };
+
+    match err.inner.downcast_ref::<IpfsError>() {
+        Some(IpfsError::Client(err)) => {
+            err.is_connect()
+                || err.is_incomplete_message()
+                || err.is_closed()
+                || err.is_canceled()
+                || err.is_timeout()
+        }
+        Some(IpfsError::IpfsClientError(err)) => {
+            std::error::Error::source(err).is_some_and(|s| s.is::<std::io::Error>())
+        }
+        _ => false,
+    }
+}
+
+/// Converts the error of the attempts of a call to IPFS into an error.
+fn retry_error(err: RetryError<Error>) -> Error {
+    match err {
+        RetryError::Failed(err) => err,
+        RetryError::Exhausted { attempts, last } => {
+            // The transient errors are always the ones returned by IPFS.
+            let last = last.and_then(|err| match err {
+                Error::External(e) => e.inner.downcast::<IpfsError>().ok().map(|e| *e),
+                _ => None,
+            });
+
+            Error::external(
+                BoxError::from(RetryError::Exhausted { attempts, last }),
+                ExternalSystem::IPFS,
+            )
+        }
+    }
+}
+
+/// A pinned CID.
+pub struct Pin {
+    pub cid: String,
+    /// The type of pin (e.g. recursive, direct).
+    pub kind: String,
+}
+
+/// Returns the multihash of `cid`, which identifies its content regardless of the CID version and
+/// multibase; IPFS may report the same content with a different CID than the one that is
+/// registered. It returns `None` if `cid` isn't a valid CID.
+pub fn multihash(cid: &str) -> Option<Vec<u8>> {
+    cid::Cid::try_from(cid).ok().map(|c| c.hash().to_bytes())
+}
+
+/// Options for uploading a directory.
+#[derive(Default)]
+pub struct DirOptions<'a> {
+    /// Wraps the directory into another directory, so the root CID corresponds to the wrapping
+    /// directory.
+    pub wrap: bool,
+    /// The path to set for the uploaded directory.
+    pub remote_path: Option<&'a str>,
+    /// Globs, relative to the directory, of the files to upload. All the files are uploaded when
+    /// it's empty.
+    pub include: &'a [String],
+    /// Globs, relative to the directory, of the files to not upload. They take precedence over
+    /// `include`.
+    pub exclude: &'a [String],
+    /// Only computes the CIDs without storing the files in IPFS, hence `remote_path` is ignored.
+    pub only_hash: bool,
+}
+
+/// Information of a directory uploaded to IPFS.
+pub struct UploadedDir {
+    /// The root directory.
+    pub root: UploadedFile,
+    /// The files and subdirectories contained in the root directory, at any depth.
+    pub entries: Vec<UploadedEntry>,
+}
+
+/// Information of a file or directory uploaded to IPFS as part of a directory.
+pub struct UploadedEntry {
+    /// The path of the entry in IPFS, which starts with the name of the uploaded directory.
+    pub path: String,
+    pub cid: String,
+    pub size: u64,
+}
+
+/// Returns the files of the `dirpath` directory tree to upload, like [`dir_files`], checking that
+/// there is at least one and that all of them can be read.
+fn upload_dir_files(dirpath: &Path, opts: &DirOptions) -> Result<Vec<(PathBuf, String)>, Error> {
+    let files = dir_files(dirpath, opts)?;
+    if files.is_empty() {
+        return Err(Error::invalid_arguments(
+            "(dirpath,opts)",
+            "the directory doesn't contain any file to upload",
+        ));
+    }
+
+    for (path, _) in &files {
+        std::fs::File::open(path).map_err(|err| match err.kind() {
+            ioErrorKind::PermissionDenied => Error::invalid_arguments(
+                "dirpath",
+                &format!("not read permissions to the file '{}'", path.display()),
+            ),
+            _ => Error::internal("system error when reading a file", BoxError::from(err)),
+        })?;
+    }
+
+    Ok(files)
+}
+
+/// Creates the multipart form that contains the `files` returned by [`dir_files`].
+/// The files are opened when the form reads them, so only the one whose content is being sent is
+/// open.
+fn dir_form(files: &[(PathBuf, String)]) -> Form<'static> {
+    let mut form = Form::default();
+    for (path, name) in files {
+        form.add_reader_file("path", LazyFile::new(path.clone()), name.clone());
+    }
+
+    form
+}
+
+/// Reader of a file which opens it on the first read and closes it once it's fully read.
+struct LazyFile {
+    path: PathBuf,
+    file: Option<std::fs::File>,
+    done: bool,
+}
+
+impl LazyFile {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            done: false,
+        }
+    }
+}
+
+impl std::io::Read for LazyFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.done {
+            return Ok(0);
+        }
+
+        let file = match &mut self.file {
+            Some(f) => f,
+            None => self.file.insert(std::fs::File::open(&self.path)?),
+        };
+
+        let n = file.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.file = None;
+            self.done = true;
+        }
+
+        Ok(n)
+    }
+}
+
+/// Returns the files of the `dirpath` directory tree which have to be uploaded according to the
+/// [`IGNORE_FILENAME`] files and the `opts` globs.
+/// Each file is returned with its canonical local path and its path in IPFS, which starts with
+/// the name of `dirpath`, even when it's a relative path such as `.`.
+pub(crate) fn dir_files(
+    dirpath: &Path,
+    opts: &DirOptions,
+) -> Result<Vec<(PathBuf, String)>, Error> {
+    use ignore::{overrides::OverrideBuilder, WalkBuilder};
+
+    if !dirpath.is_dir() {
+        return Err(Error::invalid_arguments(
+            "dirpath",
+            "directory not found or it isn't a directory",
+        ));
+    }
+    // Canonical because the name of the directory is taken from its path.
+    let dirpath = &dirpath.canonicalize().map_err(|err| {
+        Error::internal(
+            "system error when resolving the directory path",
+            BoxError::from(err),
+        )
+    })?;
+
+    let mut globs = OverrideBuilder::new(dirpath);
+    for g in opts.include {
+        globs
+            .add(g)
+            .map_err(|err| Error::invalid_arguments("opts{include}", &err.to_string()))?;
+    }
+    for g in opts.exclude {
+        globs
+            .add(&format!("!{}", g))
+            .map_err(|err| Error::invalid_arguments("opts{exclude}", &err.to_string()))?;
+    }
+    let globs = globs
+        .build()
+        .map_err(|err| Error::invalid_arguments("opts{include,exclude}", &err.to_string()))?;
+
+    let walker = WalkBuilder::new(dirpath)
+        .standard_filters(false)
+        .add_custom_ignore_filename(IGNORE_FILENAME)
+        .overrides(globs)
+        .build();
+
+    let prefix = dirpath.parent();
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = entry.map_err(|err| {
+            Error::internal(
+                "system error when reading the directory tree",
+                BoxError::from(err),
+            )
+        })?;
+
+        if !entry.file_type().is_some_and(|t| t.is_file()) || entry.file_name() == IGNORE_FILENAME {
+            continue;
+        }
+
+        let path = entry.into_path();
+        let name = match prefix {
+            Some(prefix) => path.strip_prefix(prefix).unwrap_or(&path),
+            None => &path,
+        }
+        .to_string_lossy()
+        .into_owned();
+
+        files.push((path, name));
+    }
+
+    Ok(files)
+}
+
+/// Parses the size reported by IPFS of an added file or directory.
+fn parse_size(res: &response::AddResponse) -> Result<u64, Error> {
+    res.size.parse::<u64>().map_err(|err| {
+        Error::external(
+            format!("IPFS reported an invalid size for '{}'. {}", res.name, err).into(),
+            ExternalSystem::IPFS,
+        )
+    })
+}
+
+/// Information of a file uploaded to IPFS.
+pub struct UploadedFile {
+    pub cid: String,
+    /// The size in bytes reported by IPFS, which includes the size of the file content and its
+    /// UnixFS metadata.
+    pub size: u64,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     use std::io::Write;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_client_retry() {
+        // Nothing listens on the port.
+        use ipfs_api_backend_hyper::TryFromUri;
+
+        let ipfs_cli = IpfsClient::from_host_and_port(http::uri::Scheme::HTTP, "localhost", 1)
+            .expect("an IPFS client from HTTP, localhost, and 1 port");
+        let client = Client {
+            client: &ipfs_cli,
+            retry: RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            },
+        };
+
+        let Err(err) = client.pins().await else {
+            panic!("connection refused");
+        };
+        assert!(matches!(err, Error::External(_)), "external error");
+        assert!(
+            err.to_string().contains("after 2 attempts"),
+            "connection errors are retried: {}",
+            err
+        );
+
+        let res = client.uploload_file(Path::new("not-exists"), None).await;
+        assert!(
+            matches!(res, Err(Error::InvalidArguments(_))),
+            "invalid arguments aren't retried"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_upload_slow_body() {
+        use futures::SinkExt;
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        // Fake IPFS API which reads the whole uploaded content before responding.
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = axum::Router::new().route(
+            "/api/v0/add",
+            axum::routing::post(move |body: axum::body::Bytes| async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                axum::Json(serde_json::json!({
+                    "Name": "slow.txt",
+                    "Hash": "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u",
+                    "Size": body.len().to_string(),
+                }))
+            }),
+        );
+        let server =
+            axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let port = server.local_addr().port();
+        tokio::spawn(server);
+
+        use ipfs_api_backend_hyper::TryFromUri;
+        let ipfs_cli = IpfsClient::from_host_and_port(http::uri::Scheme::HTTP, "127.0.0.1", port)
+            .expect("an IPFS client from HTTP, 127.0.0.1, and the server port");
+        let client = Client {
+            client: &ipfs_cli,
+            retry: RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(1),
+                call_timeout: Some(Duration::from_millis(50)),
+                timeout: Some(Duration::from_millis(100)),
+            },
+        };
+
+        // The content takes longer to send than the timeouts of the policy.
+        let (mut chunks, rx) = futures::channel::mpsc::channel(1);
+        tokio::spawn(async move {
+            for _ in 0..5 {
+                tokio::time::sleep(Duration::from_millis(40)).await;
+                if chunks.send(Ok(vec![b'a'; 16])).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        client
+            .upload_data(rx.into_async_read(), None)
+            .await
+            .expect("slow uploads aren't timed out");
+        assert_eq!(
+            requests.load(Ordering::SeqCst),
+            1,
+            "the content is uploaded once"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_upload_dir_root() {
+        let dir = generate_temp_dir();
+        let dirname = dir
+            .file_name()
+            .expect("temp dir name")
+            .to_string_lossy()
+            .into_owned();
+
+        // Fake IPFS API which reports the root directory before its entries and checks that the
+        // content of the files is uploaded.
+        let root_name = dirname.clone();
+        let app = axum::Router::new().route(
+            "/api/v0/add",
+            axum::routing::post(move |body: axum::body::Bytes| async move {
+                let body = String::from_utf8_lossy(&body);
+                assert!(
+                    ["file a", "file b", "file c"]
+                        .iter()
+                        .all(|c| body.contains(c)),
+                    "the content of the files is uploaded"
+                );
+                assert!(!body.contains("file d"), "ignored files aren't uploaded");
+
+                [
+                    (root_name.clone(), "QmRoot"),
+                    (format!("{}/a.txt", root_name), "QmA"),
+                ]
+                .iter()
+                .map(|(name, hash)| {
+                    format!(
+                        "{}\n",
+                        serde_json::json!({"Name": name, "Hash": hash, "Size": "6"})
+                    )
+                })
+                .collect::<String>()
+            }),
+        );
+        let server =
+            axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let port = server.local_addr().port();
+        tokio::spawn(server);
+
+        use ipfs_api_backend_hyper::TryFromUri;
+        let ipfs_cli = IpfsClient::from_host_and_port(http::uri::Scheme::HTTP, "127.0.0.1", port)
+            .expect("an IPFS client from HTTP, 127.0.0.1, and the server port");
+        let client = Client::with_client(&ipfs_cli, RetryPolicy::default());
+
+        let uploaded = client
+            .upload_dir(dir.as_path(), &DirOptions::default())
+            .await
+            .expect("no error uploading the directory");
+        assert_eq!(uploaded.root.cid, "QmRoot", "root CID");
+        assert_eq!(
+            uploaded.entries.iter().map(|e| &e.path).collect::<Vec<_>>(),
+            vec![&format!("{}/a.txt", dirname)],
+            "entries"
+        );
+
+        // The files of a relative path are named after the directory too.
+        let cwd = std::env::current_dir().expect("current dir");
+        std::env::set_current_dir(dir.as_path()).expect("change to the temp dir");
+        let uploaded = client
+            .upload_dir(Path::new("."), &DirOptions::default())
+            .await;
+        std::env::set_current_dir(cwd).expect("restore the current dir");
+        let uploaded = uploaded.expect("no error uploading the relative directory");
+        assert_eq!(
+            uploaded.root.cid, "QmRoot",
+            "root CID of the relative directory"
+        );
+    }
 
     #[test_with::http(localhost:5001)]
     #[tokio::test]
     async fn test_client_upload_file() {
         let (filepath, content_expected) = generate_temp_file();
         let ipfs_cli = &ipfs_client();
-        let client = Client::with_client(&ipfs_cli);
+        let client = Client::with_client(ipfs_cli, RetryPolicy::default());
 
         // File without remote path.
-        let cid = client
+        let uploaded = client
             .uploload_file(filepath.as_path(), None)
             .await
             .expect("no error uploading the file");
+        let cid = uploaded.cid;
         assert!(!cid.is_empty(), "CID isn't empty");
-
-        use futures::TryStreamExt;
+        assert!(
+            uploaded.size >= content_expected.len() as u64,
+            "size includes the file content"
+        );
 
         let content = ipfs_cli
             .cat(&cid)
@@ -96,11 +820,224 @@ mod test {
         let cid_reuploaded = client
             .uploload_file(filepath.as_path(), Some("/hello-ipfs.txt"))
             .await
-            .expect("no error uploading the file");
+            .expect("no error uploading the file")
+            .cid;
         assert!(!cid_reuploaded.is_empty(), "CID re-uploaded isn't empty");
         assert_eq!(cid, cid_reuploaded, "CID and CID re-uploaded are the same");
     }
 
+    #[test_with::http(localhost:5001)]
+    #[tokio::test]
+    async fn test_client_file_cid() {
+        let (filepath, _) = generate_temp_file();
+        let ipfs_cli = &ipfs_client();
+        let client = Client::with_client(ipfs_cli, RetryPolicy::default());
+
+        let cid = client
+            .file_cid(filepath.as_path())
+            .await
+            .expect("no error computing the CID of the file");
+
+        let uploaded = client
+            .uploload_file(filepath.as_path(), None)
+            .await
+            .expect("no error uploading the file");
+        assert_eq!(
+            cid, uploaded.cid,
+            "computed CID and uploaded CID are the same"
+        );
+    }
+
+    #[test_with::http(localhost:5001)]
+    #[tokio::test]
+    async fn test_client_download() {
+        let (filepath, content_expected) = generate_temp_file();
+        let ipfs_cli = &ipfs_client();
+        let client = Client::with_client(ipfs_cli, RetryPolicy::default());
+
+        let cid = client
+            .uploload_file(filepath.as_path(), None)
+            .await
+            .expect("no error uploading the file")
+            .cid;
+
+        let mut content = Vec::new();
+        let size = client
+            .download(&cid, &mut content)
+            .await
+            .expect("no error downloading the file");
+        assert_eq!(size, content.len() as u64, "size of the downloaded content");
+        assert_eq!(
+            content_expected,
+            String::from_utf8(content).unwrap(),
+            "downloaded content"
+        );
+    }
+
+    #[test]
+    fn test_multihash() {
+        assert_eq!(
+            multihash("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"),
+            multihash("bafybeiduiecxoeiqs3gyc6r7v3lymmhserldnpw62qjnhmqsulqjxjmtzi"),
+            "same content with CID v0 and v1"
+        );
+        assert_ne!(
+            multihash("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"),
+            multihash("bafkreidgvpkjawlxz6sffxzwgooowe5yt7i6wsyg236mfoks77nywkptdq"),
+            "different content"
+        );
+        assert!(multihash("fake CID").is_none(), "invalid CID");
+    }
+
+    #[test_with::http(localhost:5001)]
+    #[tokio::test]
+    async fn test_client_pins() {
+        let (filepath, _) = generate_temp_file();
+        let ipfs_cli = &ipfs_client();
+        let client = Client::with_client(ipfs_cli, RetryPolicy::default());
+
+        let cid = client
+            .uploload_file(filepath.as_path(), None)
+            .await
+            .expect("no error uploading the file")
+            .cid;
+
+        client
+            .unpin(&cid)
+            .await
+            .expect("no error unpinning the CID");
+        let pins = client.pins().await.expect("no error listing the pins");
+        assert!(!pins.iter().any(|p| p.cid == cid), "CID isn't pinned");
+
+        client.pin(&cid).await.expect("no error pinning the CID");
+        let pins = client.pins().await.expect("no error listing the pins");
+        assert!(
+            pins.iter().any(|p| p.cid == cid && p.kind == "recursive"),
+            "CID is pinned"
+        );
+    }
+
+    #[test]
+    fn test_dir_files() {
+        let dir = generate_temp_dir();
+        let dirname = dir
+            .file_name()
+            .expect("temp dir name")
+            .to_string_lossy()
+            .into_owned();
+
+        let names = |opts: &DirOptions| {
+            let mut names: Vec<String> = dir_files(dir.as_path(), opts)
+                .expect("no error listing the directory files")
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            names(&Default::default()),
+            vec![
+                format!("{}/a.txt", dirname),
+                format!("{}/b.log", dirname),
+                format!("{}/sub/c.txt", dirname),
+            ],
+            "without globs only the ignore file rules apply"
+        );
+
+        let include = [String::from("*.txt")];
+        let exclude = [String::from("sub")];
+        assert_eq!(
+            names(&DirOptions {
+                include: &include,
+                exclude: &exclude,
+                ..Default::default()
+            }),
+            vec![format!("{}/a.txt", dirname)],
+            "include and exclude globs"
+        );
+
+        assert!(
+            dir_files(&dir.join("a.txt"), &Default::default()).is_err(),
+            "a file isn't a directory"
+        );
+    }
+
+    #[test_with::http(localhost:5001)]
+    #[tokio::test]
+    async fn test_client_upload_dir() {
+        let dir = generate_temp_dir();
+        let ipfs_cli = &ipfs_client();
+        let client = Client::with_client(ipfs_cli, RetryPolicy::default());
+        let exclude = [String::from("*.log")];
+
+        let uploaded = client
+            .upload_dir(
+                dir.as_path(),
+                &DirOptions {
+                    exclude: &exclude,
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("no error uploading the directory");
+        assert!(!uploaded.root.cid.is_empty(), "root CID isn't empty");
+
+        let hashed = client
+            .upload_dir(
+                dir.as_path(),
+                &DirOptions {
+                    exclude: &exclude,
+                    only_hash: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("no error computing the CIDs of the directory");
+        assert_eq!(
+            hashed.root.cid, uploaded.root.cid,
+            "only hash computes the same root CID"
+        );
+
+        let dirname = dir
+            .file_name()
+            .expect("temp dir name")
+            .to_string_lossy()
+            .into_owned();
+        let mut paths: Vec<String> = uploaded.entries.into_iter().map(|e| e.path).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}/a.txt", dirname),
+                format!("{}/sub", dirname),
+                format!("{}/sub/c.txt", dirname),
+            ],
+            "uploaded entries"
+        );
+
+        let wrapped = client
+            .upload_dir(
+                dir.as_path(),
+                &DirOptions {
+                    wrap: true,
+                    exclude: &exclude,
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("no error uploading the directory wrapped");
+        assert_ne!(
+            uploaded.root.cid, wrapped.root.cid,
+            "wrapped directory has a different root CID"
+        );
+        assert!(
+            wrapped.entries.iter().any(|e| e.cid == uploaded.root.cid),
+            "wrapped directory contains the directory"
+        );
+    }
+
     // Test helpers.
     use ipfs_api_backend_hyper::IpfsClient;
     use mktemp::Temp;
@@ -131,4 +1068,18 @@ mod test {
 
         (temp_file, data)
     }
+
+    fn generate_temp_dir() -> Temp {
+        use std::fs;
+
+        let dir = Temp::new_dir().expect("create temp dir");
+        fs::create_dir(dir.join("sub")).expect("create sub directory");
+        fs::write(dir.join("a.txt"), "file a").expect("write file a");
+        fs::write(dir.join("b.log"), "file b").expect("write file b");
+        fs::write(dir.join("sub").join("c.txt"), "file c").expect("write file c");
+        fs::write(dir.join("sub").join("d.txt"), "file d").expect("write file d");
+        fs::write(dir.join(IGNORE_FILENAME), "d.txt\n").expect("write ignore file");
+
+        dir
+    }
 }
@@ -0,0 +1,408 @@
+//! Journals of the runs of the upload-and-register subcommand, which record the progress of a run
+//! so it can be resumed when it fails, without uploading the content again nor registering its
+//! CIDs twice.
+//!
+//! A journal is a JSON file named by the ID of its run, and the journals directory defaults to
+//! [`JOURNALS_DIR`] in the application's directory. It's written after each step of the run: the
+//! hash of the content when the run starts, the CIDs once the content is uploaded and, for each
+//! registration, the nonce of its transaction and the latest block number before sending it, its
+//! hash once it's sent and whether it's mined.
+
+use crate::{
+    error::{BoxError, Error},
+    ipfs,
+};
+
+use std::fs::{self, File};
+use std::io::ErrorKind as ioErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::core::{
+    rand::{thread_rng, Rng},
+    types::{Address, TxHash, H256, U256},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Name of the default journals directory.
+pub const JOURNALS_DIR: &str = "journals";
+
+/// The journal of a run.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct Journal {
+    /// The ID of the run.
+    pub id: String,
+    /// The time when the run started in seconds since the UNIX epoch.
+    pub started_at: u64,
+    #[serde(flatten)]
+    pub run: Run,
+    /// The registrations of the uploaded CIDs, being the first one the root CID. It's empty until
+    /// the content is uploaded.
+    pub registrations: Vec<Registration>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+/// What a run uploads and registers, which doesn't change when it's resumed.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct Run {
+    /// The absolute path of the file or directory to upload.
+    pub filepath: PathBuf,
+    pub remote_path: Option<String>,
+    /// The options for uploading a directory.
+    pub wrap: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub register_children: bool,
+    /// The hash of the content to upload. See [`content_hash`].
+    pub content_hash: H256,
+    pub owner: Address,
+    pub contract_address: Address,
+    pub chain_id: u64,
+}
+
+/// The registration of an uploaded CID.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct Registration {
+    /// The path in IPFS of the entry of an uploaded directory; `None` for the root CID.
+    pub path: Option<String>,
+    pub cid: String,
+    /// The nonce of the transaction; `None` until it's about to be sent.
+    pub nonce: Option<U256>,
+    /// The number of the latest block when the transaction was about to be sent, from which its
+    /// mined transaction is searched; `None` until it's about to be sent.
+    pub block: Option<u64>,
+    /// The hash of the transaction; `None` until it's sent. It's the hash of the mined
+    /// transaction once it's mined, which may be a replacement of the sent one.
+    pub tx_hash: Option<TxHash>,
+    pub mined: bool,
+}
+
+impl Registration {
+    /// Creates the registration of `cid` which isn't sent yet.
+    pub fn new(path: Option<String>, cid: String) -> Self {
+        Self {
+            path,
+            cid,
+            nonce: None,
+            block: None,
+            tx_hash: None,
+            mined: false,
+        }
+    }
+}
+
+impl Journal {
+    /// Creates the journal of a new run of `run` in `dir`, creating the directory if it doesn't
+    /// exist.
+    pub fn create_in(dir: &Path, run: Run) -> Result<Self, Error> {
+        fs::create_dir_all(dir).map_err(|err| {
+            Error::internal(
+                "system error when creating the journals directory",
+                BoxError::from(err),
+            )
+        })?;
+
+        let id = format!("{:016x}", thread_rng().gen::<u64>());
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let journal = Self {
+            path: dir.join(format!("{}.json", id)),
+            id,
+            started_at,
+            run,
+            registrations: Vec::new(),
+        };
+        journal.save()?;
+
+        Ok(journal)
+    }
+
+    /// Loads the journal of the run `id` from `dir`.
+    pub fn load_in(dir: &Path, id: &str) -> Result<Self, Error> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::invalid_arguments(
+                "id",
+                "the ID of a journal only contains hexadecimal digits",
+            ));
+        }
+
+        Self::load(&dir.join(format!("{}.json", id)))?.ok_or_else(|| {
+            Error::invalid_arguments(
+                "id",
+                &format!("journal '{}' not found in '{}'", id, dir.display()),
+            )
+        })
+    }
+
+    /// Returns the journal of the latest run of `owner` in `dir` which isn't finished. `None`
+    /// when there isn't any.
+    pub fn latest_in(dir: &Path, owner: Address) -> Result<Option<Self>, Error> {
+        let entries = match fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(err) if err.kind() == ioErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(Error::internal(
+                    "system error when reading the journals directory",
+                    BoxError::from(err),
+                ))
+            }
+        };
+
+        let mut latest: Option<Self> = None;
+        for entry in entries {
+            let path = entry
+                .map_err(|err| {
+                    Error::internal(
+                        "system error when reading the journals directory",
+                        BoxError::from(err),
+                    )
+                })?
+                .path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+
+            // The journals which cannot be read don't prevent resuming the rest.
+            let journal = match Self::load(&path) {
+                Ok(Some(j)) if j.run.owner == owner && !j.is_finished() => j,
+                _ => continue,
+            };
+            if latest
+                .as_ref()
+                .is_none_or(|l| journal.started_at >= l.started_at)
+            {
+                latest = Some(journal);
+            }
+        }
+
+        Ok(latest)
+    }
+
+    /// Returns true when the content is uploaded and all its registrations are mined.
+    pub fn is_finished(&self) -> bool {
+        !self.registrations.is_empty() && self.registrations.iter().all(|r| r.mined)
+    }
+
+    /// Writes the journal to its file. The file is replaced at once, so it's never left half
+    /// written.
+    pub fn save(&self) -> Result<(), Error> {
+        let internal =
+            |err: BoxError| Error::internal("system error when writing the journal", err);
+
+        let tmp = self.path.with_extension("json.tmp");
+        let file = File::create(&tmp).map_err(|err| internal(BoxError::from(err)))?;
+        serde_json::to_writer_pretty(&file, self).map_err(|err| internal(BoxError::from(err)))?;
+        file.sync_all()
+            .map_err(|err| internal(BoxError::from(err)))?;
+
+        fs::rename(&tmp, &self.path).map_err(|err| internal(BoxError::from(err)))
+    }
+
+    /// Reads the journal of the file `path`. `None` when the file doesn't exist.
+    fn load(path: &Path) -> Result<Option<Self>, Error> {
+        let content = match fs::read(path) {
+            Ok(c) => c,
+            Err(err) if err.kind() == ioErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(Error::internal(
+                    "system error when reading the journal",
+                    BoxError::from(err),
+                ))
+            }
+        };
+
+        let mut journal: Self = serde_json::from_slice(&content).map_err(|err| {
+            Error::internal(
+                &format!("the journal '{}' is corrupted", path.display()),
+                BoxError::from(err),
+            )
+        })?;
+        journal.path = path.to_path_buf();
+
+        Ok(Some(journal))
+    }
+}
+
+/// Returns the SHA-256 hash of the content to upload of the file or directory `filepath`. The
+/// hash of a directory covers the paths and the contents of the files which are uploaded
+/// according to `opts`, so it changes when any of them is added, removed or modified.
+pub fn content_hash(filepath: &Path, opts: &ipfs::DirOptions) -> Result<H256, Error> {
+    let files = if filepath.is_dir() {
+        let mut files = ipfs::dir_files(filepath, opts)?;
+        files.sort_by(|(_, a), (_, b)| a.cmp(b));
+        files
+    } else {
+        vec![(filepath.to_path_buf(), String::new())]
+    };
+
+    let mut hasher = Sha256::new();
+    for (path, name) in files {
+        let mut file = File::open(&path).map_err(|err| match err.kind() {
+            ioErrorKind::NotFound => Error::invalid_arguments(
+                "filepath",
+                &format!("file '{}' not found", path.display()),
+            ),
+            ioErrorKind::PermissionDenied => Error::invalid_arguments(
+                "filepath",
+                &format!("not read permissions to the file '{}'", path.display()),
+            ),
+            _ => Error::internal("system error when reading a file", BoxError::from(err)),
+        })?;
+
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        let size = std::io::copy(&mut file, &mut hasher).map_err(|err| {
+            Error::internal("system error when reading a file", BoxError::from(err))
+        })?;
+        hasher.update(size.to_le_bytes());
+    }
+
+    Ok(H256::from_slice(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use mktemp::Temp;
+
+    fn test_run(owner: Address) -> Run {
+        Run {
+            filepath: PathBuf::from("/tmp/file.txt"),
+            remote_path: None,
+            wrap: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            register_children: false,
+            content_hash: H256::random(),
+            owner,
+            contract_address: Address::random(),
+            chain_id: 1337,
+        }
+    }
+
+    #[test]
+    fn test_journal() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let owner = Address::random();
+
+        assert!(
+            Journal::latest_in(&dir.join("missing"), owner)
+                .expect("latest journal")
+                .is_none(),
+            "missing directory"
+        );
+
+        let mut journal = Journal::create_in(&dir, test_run(owner)).expect("create journal");
+        assert!(!journal.is_finished(), "nothing uploaded");
+
+        journal
+            .registrations
+            .push(Registration::new(None, String::from("QmRoot")));
+        journal.registrations[0].nonce = Some(U256::from(7));
+        journal.registrations[0].block = Some(42);
+        journal.save().expect("save journal");
+
+        let loaded = Journal::load_in(&dir, &journal.id).expect("load journal");
+        assert_eq!(loaded, journal, "loaded journal");
+
+        let latest = Journal::latest_in(&dir, owner)
+            .expect("latest journal")
+            .expect("there is an unfinished journal");
+        assert_eq!(latest.id, journal.id, "latest journal");
+        assert!(
+            Journal::latest_in(&dir, Address::random())
+                .expect("latest journal")
+                .is_none(),
+            "journals of other owners"
+        );
+
+        journal.registrations[0].tx_hash = Some(TxHash::random());
+        journal.registrations[0].mined = true;
+        journal.save().expect("save journal");
+        assert!(journal.is_finished(), "all the registrations are mined");
+        assert!(
+            Journal::latest_in(&dir, owner)
+                .expect("latest journal")
+                .is_none(),
+            "finished journals aren't resumed"
+        );
+
+        assert!(
+            matches!(
+                Journal::load_in(&dir, "0123456789abcdef"),
+                Err(Error::InvalidArguments(_))
+            ),
+            "unknown journal"
+        );
+        assert!(
+            matches!(
+                Journal::load_in(&dir, "../config"),
+                Err(Error::InvalidArguments(_))
+            ),
+            "invalid ID"
+        );
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        fs::create_dir(dir.join("d")).expect("create dir");
+        fs::write(dir.join("d/a.txt"), "a").expect("write file");
+        fs::write(dir.join("d/b.txt"), "b").expect("write file");
+        let opts = ipfs::DirOptions::default();
+
+        let file_hash = content_hash(&dir.join("d/a.txt"), &opts).expect("hash file");
+        assert_eq!(
+            file_hash,
+            content_hash(&dir.join("d/a.txt"), &opts).expect("hash file"),
+            "same content"
+        );
+
+        let dir_hash = content_hash(&dir.join("d"), &opts).expect("hash dir");
+        assert_ne!(file_hash, dir_hash, "file and directory");
+
+        fs::write(dir.join("d/b.txt"), "c").expect("write file");
+        assert_ne!(
+            dir_hash,
+            content_hash(&dir.join("d"), &opts).expect("hash dir"),
+            "modified file"
+        );
+
+        let exclude = vec![String::from("b.txt")];
+        let excluded = content_hash(
+            &dir.join("d"),
+            &ipfs::DirOptions {
+                exclude: &exclude,
+                ..Default::default()
+            },
+        )
+        .expect("hash dir");
+        fs::write(dir.join("d/b.txt"), "d").expect("write file");
+        assert_eq!(
+            excluded,
+            content_hash(
+                &dir.join("d"),
+                &ipfs::DirOptions {
+                    exclude: &exclude,
+                    ..Default::default()
+                },
+            )
+            .expect("hash dir"),
+            "excluded files don't change the hash"
+        );
+
+        assert!(
+            matches!(
+                content_hash(&dir.join("missing"), &opts),
+                Err(Error::InvalidArguments(_))
+            ),
+            "missing file"
+        );
+    }
+}
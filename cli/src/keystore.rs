@@ -0,0 +1,268 @@
+//! Encrypted JSON keystores, in the Web3 Secret Storage format, which contain the private keys of
+//! the CIDs' owners, so they don't have to be passed through the command-line.
+//!
+//! The application creates the keystores in the [`KEYS_DIR`] directory, unless another one is
+//! given, and names their files by the address of their key.
+
+use crate::{
+    config,
+    error::{BoxError, Error},
+};
+
+use std::fs;
+use std::io::ErrorKind as ioErrorKind;
+use std::path::{Path, PathBuf};
+
+use ethers::{
+    core::{k256::ecdsa::SigningKey, rand::thread_rng},
+    signers::{LocalWallet, Signer, WalletError},
+};
+use serde::{Deserialize, Serialize};
+
+/// Name of the environment variable which contains the password of the keystores.
+pub const ENV_PASSWORD: &str = "IPFS_CIDS_OWNERS_KEYSTORE_PASSWORD";
+
+/// Name of the directory, inside the application's one, where the keystores go by default.
+pub const KEYS_DIR: &str = "keys";
+
+/// A keystore found in a directory.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// The address of the key, when the keystore contains it or its file name is the address.
+    pub address: Option<String>,
+}
+
+/// The fields of a keystore file which are read without decrypting it.
+#[derive(Deserialize)]
+struct KeystoreFile {
+    address: Option<String>,
+    // Only present for identifying the keystore files.
+    #[serde(alias = "Crypto")]
+    #[allow(dead_code)]
+    crypto: serde_json::Value,
+}
+
+/// Returns the path of the keystore `path`. When `path` doesn't exist and it's only a file name,
+/// it returns the path of the file with that name in the default directory, so keystores created
+/// by the application can be referenced by their address.
+pub fn resolve(path: &Path) -> PathBuf {
+    if path.exists() || path.components().count() != 1 {
+        return path.to_path_buf();
+    }
+
+    match config::app_dir(KEYS_DIR) {
+        Some(d) if d.join(path).exists() => d.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Encrypts `key` with `password` into a new keystore in `dir`, creating the directory if it
+/// doesn't exist. The name of the file is `name` or the address of the key when it's `None`.
+/// It returns the path of the created keystore and the wallet of the key.
+pub fn create(
+    dir: &Path,
+    key: &SigningKey,
+    password: &str,
+    name: Option<&str>,
+) -> Result<(PathBuf, LocalWallet), Error> {
+    let wallet = LocalWallet::from(key.clone());
+    let name = match name {
+        Some(n) => String::from(n),
+        None => format!("{:?}", wallet.address()),
+    };
+
+    let path = dir.join(&name);
+    if path.exists() {
+        return Err(Error::invalid_arguments(
+            "name",
+            &format!("a file named '{}' already exists in the directory", name),
+        ));
+    }
+
+    fs::create_dir_all(dir).map_err(|err| {
+        Error::internal(
+            "system error when creating the keystores directory",
+            BoxError::from(err),
+        )
+    })?;
+
+    eth_keystore::encrypt_key(
+        dir,
+        &mut thread_rng(),
+        key.to_bytes(),
+        password,
+        Some(&name),
+    )
+    .map_err(|err| {
+        Error::internal(
+            "system error when writing the keystore file",
+            BoxError::from(err),
+        )
+    })?;
+
+    Ok((path, wallet))
+}
+
+/// Decrypts the keystore located in `path` with `password`.
+pub fn decrypt(path: &Path, password: &str) -> Result<LocalWallet, Error> {
+    LocalWallet::decrypt_keystore(path, password).map_err(|err| match err {
+        WalletError::EthKeystoreError(eth_keystore::KeystoreError::MacMismatch) => {
+            Error::invalid_arguments("password", "wrong password for the keystore")
+        }
+        WalletError::EthKeystoreError(eth_keystore::KeystoreError::StdIo(msg)) => {
+            Error::invalid_arguments("path", &format!("cannot read the keystore file. {}", msg))
+        }
+        _ => Error::invalid_arguments("path", &format!("invalid keystore file. {}", err)),
+    })
+}
+
+/// Lists the keystores of `dir` sorted by path. The files which aren't keystores are ignored.
+/// It returns an empty list if the directory doesn't exist.
+pub fn list(dir: &Path) -> Result<Vec<Entry>, Error> {
+    let internal =
+        |err: BoxError| Error::internal("system error when reading the keystores directory", err);
+
+    let read_dir = match fs::read_dir(dir) {
+        Ok(r) => r,
+        Err(err) if err.kind() == ioErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(internal(err.into())),
+    };
+
+    let mut entries = Vec::new();
+    for e in read_dir {
+        let path = e.map_err(|err| internal(err.into()))?.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let keystore: KeystoreFile = match fs::read(&path)
+            .ok()
+            .and_then(|c| serde_json::from_slice(&c).ok())
+        {
+            Some(k) => k,
+            None => continue,
+        };
+
+        let address = keystore
+            .address
+            .map(|a| {
+                if a.starts_with("0x") {
+                    a
+                } else {
+                    format!("0x{}", a)
+                }
+            })
+            .or_else(|| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .filter(|n| n.parse::<ethers::types::Address>().is_ok())
+                    .map(String::from)
+            });
+
+        entries.push(Entry { path, address });
+    }
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Reads the password of a keystore from the file `password_file`, if it's set, otherwise from
+/// the environment variable [`ENV_PASSWORD`], if it's set, otherwise it prompts for it.
+/// When `confirm` is true, the prompt asks for the password twice.
+pub fn read_password(password_file: Option<&Path>, confirm: bool) -> Result<String, Error> {
+    if let Some(p) = password_file {
+        let password = fs::read_to_string(p).map_err(|err| match err.kind() {
+            ioErrorKind::NotFound => {
+                Error::invalid_arguments("password_file", "password file not found")
+            }
+            _ => Error::internal(
+                "system error when reading the password file",
+                BoxError::from(err),
+            ),
+        })?;
+        // Editors usually add a trailing new line to the files.
+        return Ok(String::from(password.trim_end_matches(['\r', '\n'])));
+    }
+
+    if let Ok(password) = std::env::var(ENV_PASSWORD) {
+        return Ok(password);
+    }
+
+    let prompt = |msg: &str| {
+        rpassword::prompt_password(msg).map_err(|err| {
+            Error::internal(
+                "system error when prompting the password",
+                BoxError::from(err),
+            )
+        })
+    };
+
+    let password = prompt("Keystore password: ")?;
+    if confirm && password != prompt("Repeat the keystore password: ")? {
+        return Err(Error::invalid_arguments(
+            "password",
+            "passwords don't match",
+        ));
+    }
+
+    Ok(password)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use mktemp::Temp;
+
+    #[test]
+    fn test_create_list_decrypt() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let key = SigningKey::random(&mut thread_rng());
+
+        let (path, wallet) = create(&dir, &key, "secret", None).expect("create keystore");
+        let address = format!("{:?}", wallet.address());
+        assert_eq!(path, dir.join(&address), "keystore named by its address");
+        assert!(
+            create(&dir, &key, "secret", None).is_err(),
+            "keystore already exists"
+        );
+
+        fs::write(dir.join("not-a-keystore.json"), "{}").expect("write other file");
+        assert_eq!(
+            list(&dir).expect("list keystores"),
+            vec![Entry {
+                path: path.clone(),
+                address: Some(address),
+            }],
+            "keystores"
+        );
+
+        assert_eq!(
+            decrypt(&path, "secret").expect("decrypt keystore"),
+            wallet,
+            "decrypted wallet"
+        );
+        assert!(
+            matches!(decrypt(&path, "wrong"), Err(Error::InvalidArguments(_))),
+            "wrong password"
+        );
+    }
+
+    #[test]
+    fn test_read_password_file() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let path = dir.join("password");
+        fs::write(&path, "secret\n").expect("write password file");
+
+        assert_eq!(
+            read_password(Some(&path), true).expect("read password"),
+            "secret",
+            "password without trailing new line"
+        );
+        assert!(
+            read_password(Some(&dir.join("missing")), false).is_err(),
+            "missing password file"
+        );
+    }
+}
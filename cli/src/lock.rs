@@ -0,0 +1,105 @@
+//! Locks which prevent several processes from sending transactions of the same owner at the same
+//! time, because each process assigns the nonces of the transactions locally and they would
+//! collide.
+//!
+//! The lock of an owner is an exclusive lock of a file named by its address. The lock files are
+//! kept under the application's directory (see [`config::app_dir`]) and the lock is released when
+//! the process ends, even if it crashes.
+
+use crate::{
+    config,
+    error::{BoxError, Error},
+};
+
+use std::fs::{self, File};
+use std::path::Path;
+
+use ethers::core::types::Address;
+use fs2::FileExt;
+
+/// Directory of the lock files.
+const LOCKS_DIR: &str = "locks";
+
+/// The lock of an owner. It's held until it's dropped.
+#[derive(Debug)]
+pub struct OwnerLock {
+    // The lock is released when the file is closed.
+    _file: File,
+}
+
+impl OwnerLock {
+    /// Acquires the lock of `owner` in the default directory. It fails when another process holds
+    /// it.
+    pub fn acquire(owner: Address) -> Result<Self, Error> {
+        let dir = config::app_dir(LOCKS_DIR).ok_or_else(|| {
+            Error::internal(
+                "the locks directory cannot be found",
+                BoxError::from("the user's configuration directory isn't defined"),
+            )
+        })?;
+
+        Self::acquire_in(&dir, owner)
+    }
+
+    /// Acquires the lock of `owner` in `dir`, creating the directory if it doesn't exist. It fails
+    /// when another process holds it.
+    pub fn acquire_in(dir: &Path, owner: Address) -> Result<Self, Error> {
+        fs::create_dir_all(dir).map_err(|err| {
+            Error::internal(
+                "system error when creating the locks directory",
+                BoxError::from(err),
+            )
+        })?;
+
+        let path = dir.join(format!("{:?}.lock", owner));
+        let file = File::create(&path).map_err(|err| {
+            Error::internal(
+                "system error when creating the lock file",
+                BoxError::from(err),
+            )
+        })?;
+
+        file.try_lock_exclusive().map_err(|_| {
+            Error::invalid_arguments(
+                "owner",
+                &format!(
+                    "another process is sending transactions of the owner {:?}, it holds the lock '{}'",
+                    owner,
+                    path.display()
+                ),
+            )
+        })?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use mktemp::Temp;
+
+    #[test]
+    fn test_owner_lock() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let owner = Address::random();
+
+        let lock = OwnerLock::acquire_in(&dir, owner).expect("acquire the lock");
+        assert!(
+            dir.join(format!("{:?}.lock", owner)).is_file(),
+            "lock file exists"
+        );
+        assert!(
+            matches!(
+                OwnerLock::acquire_in(&dir, owner),
+                Err(Error::InvalidArguments(_))
+            ),
+            "the lock is held"
+        );
+        OwnerLock::acquire_in(&dir, Address::random()).expect("acquire the lock of another owner");
+
+        drop(lock);
+        OwnerLock::acquire_in(&dir, owner).expect("acquire the released lock");
+    }
+}
@@ -3,17 +3,200 @@
 
 mod cli;
 mod cmd;
+mod config;
 mod error;
 mod ethereum;
+mod grpc;
 mod ipfs;
-
-use clap::Parser;
+mod journal;
+mod keystore;
+mod lock;
+mod manifest;
+mod mnemonic;
+mod pkcs11;
+mod retry;
+mod server;
+mod signer;
+mod watch;
 
 #[tokio::main]
 async fn main() {
-    let args = cli::App::parse();
-    match cmd::upload_and_register(args).await {
-        Ok(summary) => println!("{}", summary),
-        Err(err) => println!("{}", err),
+    let args = match cli::parse() {
+        Ok(a) => a,
+        Err(err) => {
+            eprintln!("{}", format_error(&err, cli::output_format()));
+            std::process::exit(err.exit_code());
+        }
     };
+    let output = args.output;
+    let retry = cmd::retry_policy(&args.retry);
+
+    if let Err(err) = run(args.command, output, retry).await {
+        eprintln!("{}", format_error(&err, output));
+        std::process::exit(err.exit_code());
+    }
+}
+
+/// Formats `err` for printing it to the standard error in the `output` format. In JSON format,
+/// it's an object with the error in its `error` field.
+fn format_error(err: &error::Error, output: cli::OutputFormat) -> String {
+    match output {
+        cli::OutputFormat::Table => err.to_string(),
+        cli::OutputFormat::Json => format(&serde_json::json!({ "error": err }), output),
+    }
+}
+
+/// Executes `command`, retrying its calls to IPFS and Ethereum with `retry`, and prints its result
+/// to the standard output in the `output` format.
+async fn run(
+    command: cli::Command,
+    output: cli::OutputFormat,
+    retry: retry::RetryPolicy,
+) -> Result<(), error::Error> {
+    match command {
+        cli::Command::UploadAndRegister(args) if args.dry_run => println!(
+            "{}",
+            format(
+                &cmd::upload_and_register_dry_run(args, retry).await?,
+                output
+            )
+        ),
+        cli::Command::UploadAndRegister(args) => {
+            println!(
+                "{}",
+                format(&cmd::upload_and_register(args, retry).await?, output)
+            )
+        }
+        cli::Command::Resume(args) => {
+            println!("{}", format(&cmd::resume(args, retry).await?, output))
+        }
+        cli::Command::Upload(args) => {
+            println!("{}", format(&cmd::upload(args, retry).await?, output))
+        }
+        cli::Command::Register(args) if args.dry_run => {
+            println!(
+                "{}",
+                format(&cmd::register_dry_run(args, retry).await?, output)
+            )
+        }
+        cli::Command::Register(args) => {
+            println!("{}", format(&cmd::register(args, retry).await?, output))
+        }
+        cli::Command::Batch(args) if args.dry_run => {
+            println!(
+                "{}",
+                format(&cmd::batch_dry_run(args, retry).await?, output)
+            )
+        }
+        cli::Command::Batch(args) => {
+            println!("{}", format(&cmd::batch(args, retry).await?, output))
+        }
+        cli::Command::Watch(args) => cmd::watch(args, retry, output).await?,
+        cli::Command::Serve(args) => cmd::serve(args, retry).await?,
+        cli::Command::ServeGrpc(args) => cmd::serve_grpc(args, retry).await?,
+        cli::Command::List(args) => println!("{}", format(&cmd::list(args, retry).await?, output)),
+        cli::Command::Fetch(args) => {
+            let summary = cmd::fetch(args, retry).await?;
+            // The summary goes to the standard error when the content is written to the standard
+            // output for not mixing them.
+            if summary.out.is_none() {
+                eprintln!("{}", format(&summary, output));
+            } else {
+                println!("{}", format(&summary, output));
+            }
+        }
+        cli::Command::Verify(args) => {
+            println!("{}", format(&cmd::verify(args, retry).await?, output))
+        }
+        cli::Command::Pin(cli::PinCommand::Add(args)) => {
+            println!("{}", format(&cmd::pin_add(args, retry).await?, output))
+        }
+        cli::Command::Pin(cli::PinCommand::Rm(args)) => {
+            println!("{}", format(&cmd::pin_rm(args, retry).await?, output))
+        }
+        cli::Command::Pin(cli::PinCommand::Ls(args)) => {
+            println!("{}", format(&cmd::pin_ls(args, retry).await?, output))
+        }
+        cli::Command::Pin(cli::PinCommand::Verify(args)) => {
+            println!("{}", format(&cmd::pin_verify(args, retry).await?, output))
+        }
+        cli::Command::Tx(cli::TxCommand::Status(args)) => {
+            println!("{}", format(&cmd::tx_status(args, retry).await?, output))
+        }
+        cli::Command::Tx(cli::TxCommand::Speedup(args)) => {
+            println!("{}", format(&cmd::tx_speedup(args, retry).await?, output))
+        }
+        cli::Command::Tx(cli::TxCommand::Cancel(args)) => {
+            println!("{}", format(&cmd::tx_cancel(args, retry).await?, output))
+        }
+        cli::Command::Keys(cli::KeysCommand::New(args)) => {
+            println!("{}", format(&cmd::keys_new(args)?, output))
+        }
+        cli::Command::Keys(cli::KeysCommand::Import(args)) => {
+            println!("{}", format(&cmd::keys_import(args)?, output))
+        }
+        cli::Command::Keys(cli::KeysCommand::List(args)) => {
+            println!("{}", format(&cmd::keys_list(args)?, output))
+        }
+        cli::Command::Keys(cli::KeysCommand::Derive(args)) => {
+            println!("{}", format(&cmd::keys_derive(args)?, output))
+        }
+        cli::Command::Config(cli::ConfigCommand::Show(args)) => {
+            println!("{}", format(&cmd::config_show(args), output))
+        }
+    };
+
+    Ok(())
+}
+
+/// Formats `value` in the specified `format`.
+fn format<T>(value: &T, format: cli::OutputFormat) -> String
+where
+    T: std::fmt::Display + serde::Serialize,
+{
+    match format {
+        cli::OutputFormat::Table => value.to_string(),
+        cli::OutputFormat::Json => match serde_json::to_string_pretty(value) {
+            Ok(json) => json,
+            Err(err) => format!("error serializing the output to JSON. {}", err),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::ffi::OsString;
+
+    use mktemp::Temp;
+
+    #[test]
+    fn test_format_error_parse() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let config = dir.join("config.toml");
+        std::fs::write(&config, "[profiles.local]\nunknown = 1\n").expect("write config file");
+
+        let args: Vec<OsString> = [
+            "ipfs-cids-owners-cli",
+            "--output",
+            "json",
+            "--config",
+            &config.to_string_lossy(),
+            "list",
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+
+        let err = match cli::parse_from(args.clone()) {
+            Ok(_) => panic!("expected an invalid profile error"),
+            Err(err) => err,
+        };
+        assert_eq!(err.exit_code(), 5, "invalid arguments exit code");
+
+        let output = format_error(&err, cli::output_arg(&args));
+        let json: serde_json::Value = serde_json::from_str(&output).expect("JSON error");
+        assert_eq!(json["error"]["kind"], "InvalidArguments", "error kind");
+    }
 }
@@ -0,0 +1,201 @@
+//! Manifest files which list the files to upload and register in batch mode and the results of
+//! processing them.
+//!
+//! The format of a manifest file is identified by its extension, which can be `csv` or `json`.
+//! CSV files must have a header row with the names of the fields and JSON files must contain an
+//! array of objects.
+
+use crate::error::{BoxError, Error};
+
+use std::fs::File;
+use std::io::ErrorKind as ioErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A file to upload and register.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Row {
+    pub filepath: PathBuf,
+    /// The path to set for the uploaded file.
+    #[serde(default)]
+    pub remote_path: Option<String>,
+}
+
+/// The result of uploading and registering the file of a [`Row`].
+/// `error` is only set when the file couldn't be uploaded or registered, however, `cid` is set
+/// when the file was uploaded although it couldn't be registered.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResultRow {
+    pub filepath: PathBuf,
+    pub remote_path: Option<String>,
+    pub cid: Option<String>,
+    pub ether_tx_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Formats supported by the manifest files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    /// Returns the format corresponding to the extension of `path`.
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("csv") => Ok(Format::Csv),
+            Some(e) if e.eq_ignore_ascii_case("json") => Ok(Format::Json),
+            _ => Err(Error::invalid_arguments(
+                "path",
+                "manifest file extension must be 'csv' or 'json'",
+            )),
+        }
+    }
+}
+
+/// Reads the rows of the manifest file located in `path`.
+pub fn read(path: &Path) -> Result<Vec<Row>, Error> {
+    let format = Format::from_path(path)?;
+    let file = File::open(path).map_err(|err| match err.kind() {
+        ioErrorKind::NotFound => Error::invalid_arguments("path", "manifest file not found"),
+        ioErrorKind::PermissionDenied => {
+            Error::invalid_arguments("path", "not read permissions to the manifest file")
+        }
+        _ => Error::internal(
+            "system error when opening the manifest file",
+            BoxError::from(err),
+        ),
+    })?;
+
+    let invalid = |err: &dyn std::fmt::Display| {
+        Error::invalid_arguments("path", &format!("invalid manifest file. {}", err))
+    };
+
+    match format {
+        Format::Csv => csv::Reader::from_reader(file)
+            .deserialize()
+            .collect::<Result<Vec<Row>, _>>()
+            .map_err(|err| invalid(&err)),
+        Format::Json => {
+            serde_json::from_reader(std::io::BufReader::new(file)).map_err(|err| invalid(&err))
+        }
+    }
+}
+
+/// Writes `rows` to the manifest file located in `path`, replacing its content if it exists.
+pub fn write(path: &Path, rows: &[ResultRow]) -> Result<(), Error> {
+    let format = Format::from_path(path)?;
+    let file = File::create(path).map_err(|err| match err.kind() {
+        ioErrorKind::PermissionDenied => {
+            Error::invalid_arguments("path", "not write permissions to the manifest file")
+        }
+        _ => Error::internal(
+            "system error when creating the manifest file",
+            BoxError::from(err),
+        ),
+    })?;
+
+    let internal = |err: BoxError| Error::internal("system error when writing the manifest", err);
+
+    match format {
+        Format::Csv => {
+            let mut w = csv::Writer::from_writer(file);
+            for r in rows {
+                w.serialize(r)
+                    .map_err(|err| internal(BoxError::from(err)))?;
+            }
+            w.flush().map_err(|err| internal(BoxError::from(err)))
+        }
+        Format::Json => serde_json::to_writer_pretty(std::io::BufWriter::new(file), rows)
+            .map_err(|err| internal(BoxError::from(err))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use mktemp::Temp;
+
+    #[test]
+    fn test_read() {
+        let dir = Temp::new_dir().expect("create temp dir");
+
+        let csv_path = dir.join("manifest.csv");
+        std::fs::write(
+            &csv_path,
+            "filepath,remote_path\na.txt,/a.txt\n/tmp/b.txt,\n",
+        )
+        .expect("write CSV manifest");
+
+        let json_path = dir.join("manifest.json");
+        std::fs::write(
+            &json_path,
+            r#"[{"filepath": "a.txt", "remote_path": "/a.txt"}, {"filepath": "/tmp/b.txt"}]"#,
+        )
+        .expect("write JSON manifest");
+
+        let expected = vec![
+            Row {
+                filepath: PathBuf::from("a.txt"),
+                remote_path: Some(String::from("/a.txt")),
+            },
+            Row {
+                filepath: PathBuf::from("/tmp/b.txt"),
+                remote_path: None,
+            },
+        ];
+
+        assert_eq!(read(&csv_path).expect("read CSV manifest"), expected, "CSV");
+        assert_eq!(
+            read(&json_path).expect("read JSON manifest"),
+            expected,
+            "JSON"
+        );
+        assert!(
+            read(&dir.join("manifest.txt")).is_err(),
+            "unsupported extension"
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let rows = vec![
+            ResultRow {
+                filepath: PathBuf::from("a.txt"),
+                remote_path: Some(String::from("/a.txt")),
+                cid: Some(String::from(
+                    "QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u",
+                )),
+                ether_tx_hash: Some(String::from("0x01")),
+                error: None,
+            },
+            ResultRow {
+                filepath: PathBuf::from("b.txt"),
+                remote_path: None,
+                cid: None,
+                ether_tx_hash: None,
+                error: Some(String::from("filepath arguments have invalid values")),
+            },
+        ];
+
+        for name in ["result.csv", "result.json"] {
+            let path = dir.join(name);
+            write(&path, &rows).expect("write result manifest");
+
+            let written: Vec<ResultRow> = match Format::from_path(&path).unwrap() {
+                Format::Csv => csv::Reader::from_path(&path)
+                    .expect("open CSV result manifest")
+                    .deserialize()
+                    .collect::<Result<_, _>>()
+                    .expect("read CSV result manifest"),
+                Format::Json => serde_json::from_reader(File::open(&path).unwrap())
+                    .expect("read JSON result manifest"),
+            };
+            assert_eq!(written, rows, "{}", name);
+        }
+    }
+}
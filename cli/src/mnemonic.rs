@@ -0,0 +1,103 @@
+//! Derivation of the CIDs' owners wallets from a BIP-39 mnemonic phrase (English word list)
+//! through BIP-32 hierarchical deterministic paths, so several owner accounts are managed from a
+//! single mnemonic.
+
+use crate::error::{BoxError, Error};
+
+use std::fs;
+use std::io::ErrorKind as ioErrorKind;
+use std::path::Path;
+
+use ethers::{
+    core::types::PathOrString,
+    signers::{coins_bip39::English, LocalWallet, MnemonicBuilder},
+};
+
+/// Derivation path prefix of the Ethereum accounts, to which the account index is appended.
+pub const DEFAULT_PATH_PREFIX: &str = "m/44'/60'/0'/0/";
+
+/// Reads the mnemonic phrase from the file located in `path`.
+pub fn read_phrase(path: &Path) -> Result<String, Error> {
+    let phrase = fs::read_to_string(path).map_err(|err| match err.kind() {
+        ioErrorKind::NotFound => {
+            Error::invalid_arguments("mnemonic_file", "mnemonic file not found")
+        }
+        ioErrorKind::PermissionDenied => {
+            Error::invalid_arguments("mnemonic_file", "not read permissions to the mnemonic file")
+        }
+        _ => Error::internal(
+            "system error when reading the mnemonic file",
+            BoxError::from(err),
+        ),
+    })?;
+
+    // Normalize the white spaces, so the words can be in several lines.
+    Ok(phrase.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Returns the derivation path of the account with `index` of the `prefix` path, which must end
+/// with a slash.
+pub fn path_for_index(prefix: &str, index: u32) -> String {
+    format!("{}{}", prefix, index)
+}
+
+/// Derives the wallet of the derivation `path` from the mnemonic `phrase`.
+pub fn derive(phrase: &str, path: &str) -> Result<LocalWallet, Error> {
+    MnemonicBuilder::<English>::default()
+        .phrase(PathOrString::String(String::from(phrase)))
+        .derivation_path(path)
+        .map_err(|err| {
+            Error::invalid_arguments(
+                "derivation_path",
+                &format!("invalid derivation path. {}", err),
+            )
+        })?
+        .build()
+        .map_err(|err| {
+            Error::invalid_arguments("mnemonic", &format!("invalid mnemonic phrase. {}", err))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ethers::signers::Signer;
+    use mktemp::Temp;
+
+    /// Mnemonic used by the local Ethereum development nodes.
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_derive() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let path = dir.join("mnemonic");
+        fs::write(
+            &path,
+            "test test test test test test\ntest test test test test junk\n",
+        )
+        .expect("write mnemonic file");
+
+        let phrase = read_phrase(&path).expect("read mnemonic file");
+        assert_eq!(phrase, PHRASE, "normalized phrase");
+
+        let expected = [
+            "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+            "0x70997970c51812dc3a010c7d01b50e0d17dc79c8",
+        ];
+        for (i, e) in expected.iter().enumerate() {
+            let wallet = derive(&phrase, &path_for_index(DEFAULT_PATH_PREFIX, i as u32))
+                .expect("derive wallet");
+            assert_eq!(format!("{:?}", wallet.address()), *e, "index {}", i);
+        }
+
+        assert!(
+            derive(&phrase, "m/invalid").is_err(),
+            "invalid derivation path"
+        );
+        assert!(
+            derive("test test", DEFAULT_PATH_PREFIX).is_err(),
+            "invalid phrase"
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! Signer of the transactions of the CIDs' owners with secp256k1 keys held in a PKCS#11 token
+//! (e.g. an HSM), so the keys never leave it.
+//!
+//! The key is selected by the label of its private key object, and its public key object must
+//! have the same label, because the address of the owner is computed from it.
+
+use crate::error::{BoxError, Error, ExternalSystem};
+
+use std::fs;
+use std::io::ErrorKind as ioErrorKind;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use cryptoki::{
+    context::{CInitializeArgs, Pkcs11},
+    error::{Error as Pkcs11Error, RvError},
+    mechanism::Mechanism,
+    object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle},
+    session::{Session, UserType},
+    types::AuthPin,
+};
+use ethers::{
+    core::types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Address, Signature, H256, U256,
+    },
+    signers::{to_eip155_v, Signer},
+    utils::{hash_message, keccak256},
+};
+
+/// Name of the environment variable which contains the user PIN of the token.
+pub const ENV_PIN: &str = "IPFS_CIDS_OWNERS_PKCS11_PIN";
+
+/// DER encoding of the object identifier of the secp256k1 curve (1.3.132.0.10).
+const SECP256K1_OID: [u8; 7] = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+/// Order of the secp256k1 curve.
+const SECP256K1_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// Signs with a secp256k1 key held in a PKCS#11 token.
+#[derive(Clone)]
+pub struct Pkcs11Signer {
+    /// The session is logged in as user for signing with the private key.
+    session: Arc<Mutex<Session>>,
+    key: ObjectHandle,
+    label: String,
+    address: Address,
+    chain_id: u64,
+}
+
+impl Pkcs11Signer {
+    /// Creates a new instance which signs with the key labeled `label` of the token of the slot
+    /// with ID `slot` using the PKCS#11 `module` (i.e. the path of its shared library).
+    /// The chain ID is 1 (i.e. Ethereum mainnet) until it's set with [`Signer::with_chain_id`].
+    pub fn new(module: &Path, slot: u64, label: &str, pin: &str) -> Result<Self, Error> {
+        let ctx = Pkcs11::new(module).map_err(|err| {
+            Error::invalid_arguments(
+                "pkcs11_module",
+                &format!("cannot load the PKCS#11 module. {}", err),
+            )
+        })?;
+        match ctx.initialize(CInitializeArgs::OsThreads) {
+            Ok(()) | Err(Pkcs11Error::AlreadyInitialized) => {}
+            Err(err) => return Err(external(err)),
+        }
+
+        let slot = ctx
+            .get_slots_with_token()
+            .map_err(external)?
+            .into_iter()
+            .find(|s| s.id() == slot)
+            .ok_or_else(|| {
+                Error::invalid_arguments("pkcs11_slot", "slot not found or without a token")
+            })?;
+
+        let session = ctx.open_ro_session(slot).map_err(external)?;
+        session
+            .login(UserType::User, Some(&AuthPin::new(String::from(pin))))
+            .map_err(|err| match err {
+                Pkcs11Error::Pkcs11(RvError::PinIncorrect) => {
+                    Error::invalid_arguments("pin", "wrong user PIN for the token")
+                }
+                _ => external(err),
+            })?;
+
+        let key = find_key(&session, ObjectClass::PRIVATE_KEY, "private key", label)?;
+        let public_key = find_key(&session, ObjectClass::PUBLIC_KEY, "public key", label)?;
+
+        let attrs = session
+            .get_attributes(
+                public_key,
+                &[AttributeType::EcParams, AttributeType::EcPoint],
+            )
+            .map_err(external)?;
+        let mut point = None;
+        for attr in attrs {
+            match attr {
+                Attribute::EcParams(params) if params != SECP256K1_OID => {
+                    return Err(Error::invalid_arguments(
+                        "pkcs11_label",
+                        "the key isn't a secp256k1 key",
+                    ))
+                }
+                Attribute::EcPoint(p) => point = Some(p),
+                _ => {}
+            }
+        }
+
+        let address = point
+            .as_deref()
+            .and_then(address_from_ec_point)
+            .ok_or_else(|| {
+                Error::invalid_arguments(
+                    "pkcs11_label",
+                    "the public key of the key doesn't have a valid uncompressed EC point",
+                )
+            })?;
+
+        Ok(Self {
+            session: Arc::new(Mutex::new(session)),
+            key,
+            label: String::from(label),
+            address,
+            chain_id: 1,
+        })
+    }
+
+    /// Signs `hash` with the key of the token. The `v` of the returned signature is 27 or 28.
+    fn sign_hash(&self, hash: H256) -> Result<Signature, Error> {
+        let raw = self
+            .session
+            .lock()
+            .map_err(|_| {
+                Error::internal(
+                    "BUG a thread panicked while signing with the PKCS#11 token",
+                    BoxError::from("poisoned PKCS#11 session lock"),
+                )
+            })?
+            .sign(&Mechanism::Ecdsa, self.key, hash.as_bytes())
+            .map_err(external)?;
+
+        signature(hash, &raw, self.address)
+    }
+}
+
+impl std::fmt::Debug for Pkcs11Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pkcs11Signer")
+            .field("label", &self.label)
+            .field("address", &self.address)
+            .field("chain_id", &self.chain_id)
+            .finish()
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for Pkcs11Signer {
+    type Error = Error;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        self.sign_hash(hash_message(message))
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        // The RLP encoding of the transaction must have the same chain ID as `v` of the
+        // signature.
+        let mut tx = tx.clone();
+        let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(self.chain_id);
+        tx.set_chain_id(chain_id);
+
+        let mut sig = self.sign_hash(tx.sighash())?;
+        sig.v = to_eip155_v(sig.v as u8 - 27, chain_id);
+        Ok(sig)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        let hash = payload.encode_eip712().map_err(|err| {
+            Error::invalid_arguments("payload", &format!("invalid EIP-712 payload. {}", err))
+        })?;
+        self.sign_hash(H256::from(hash))
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+/// Reads the user PIN of the token from the file `pin_file`, if it's set, otherwise from the
+/// environment variable [`ENV_PIN`], if it's set, otherwise it prompts for it.
+pub fn read_pin(pin_file: Option<&Path>) -> Result<String, Error> {
+    if let Some(p) = pin_file {
+        let pin = fs::read_to_string(p).map_err(|err| match err.kind() {
+            ioErrorKind::NotFound => Error::invalid_arguments("pin_file", "PIN file not found"),
+            _ => Error::internal(
+                "system error when reading the PIN file",
+                BoxError::from(err),
+            ),
+        })?;
+        // Editors usually add a trailing new line to the files.
+        return Ok(String::from(pin.trim_end_matches(['\r', '\n'])));
+    }
+
+    if let Ok(pin) = std::env::var(ENV_PIN) {
+        return Ok(pin);
+    }
+
+    rpassword::prompt_password("PKCS#11 user PIN: ")
+        .map_err(|err| Error::internal("system error when prompting the PIN", BoxError::from(err)))
+}
+
+/// Finds the only EC key object of `class`, whose name is `class_name`, with `label`.
+fn find_key(
+    session: &Session,
+    class: ObjectClass,
+    class_name: &str,
+    label: &str,
+) -> Result<ObjectHandle, Error> {
+    let keys = session
+        .find_objects(&[
+            Attribute::Class(class),
+            Attribute::KeyType(KeyType::EC),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ])
+        .map_err(external)?;
+
+    match keys.as_slice() {
+        [key] => Ok(*key),
+        [] => Err(Error::invalid_arguments(
+            "pkcs11_label",
+            &format!("no EC {} labeled '{}' in the token", class_name, label),
+        )),
+        _ => Err(Error::invalid_arguments(
+            "pkcs11_label",
+            &format!("several EC {} labeled '{}' in the token", class_name, label),
+        )),
+    }
+}
+
+/// Returns the address of the public key of the uncompressed EC `point`, which may be DER
+/// encoded as an octet string, as the PKCS#11 specification requires, or not, as some tokens do.
+/// `None` if `point` isn't an uncompressed EC point.
+fn address_from_ec_point(point: &[u8]) -> Option<Address> {
+    let point = match point {
+        [0x04, 0x41, rest @ ..] if rest.len() == 65 => rest,
+        p => p,
+    };
+
+    match point {
+        [0x04, coordinates @ ..] if coordinates.len() == 64 => {
+            Some(Address::from_slice(&keccak256(coordinates)[12..]))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the signature of the raw ECDSA signature `raw` (i.e. `r` and `s`) of `hash` made by
+/// the key of `address`. The PKCS#11 tokens don't return the recovery ID, so `v` is the one that
+/// recovers `address`, and they may return a high `s`, which Ethereum rejects (EIP-2), so it's
+/// normalized.
+fn signature(hash: H256, raw: &[u8], address: Address) -> Result<Signature, Error> {
+    if raw.len() != 64 {
+        return Err(Error::external(
+            BoxError::from(format!(
+                "invalid ECDSA signature length {}, expected 64",
+                raw.len()
+            )),
+            ExternalSystem::Pkcs11,
+        ));
+    }
+
+    let order = U256::from_big_endian(&SECP256K1_ORDER);
+    let r = U256::from_big_endian(&raw[..32]);
+    let mut s = U256::from_big_endian(&raw[32..]);
+    if s > order / 2 {
+        s = order - s;
+    }
+
+    for v in [27, 28] {
+        let sig = Signature { r, s, v };
+        if sig.recover(hash).ok() == Some(address) {
+            return Ok(sig);
+        }
+    }
+
+    Err(Error::external(
+        BoxError::from("the ECDSA signature doesn't correspond to the public key of the key"),
+        ExternalSystem::Pkcs11,
+    ))
+}
+
+/// Maps a PKCS#11 error to an error of the token.
+fn external(err: Pkcs11Error) -> Error {
+    Error::external(BoxError::from(err), ExternalSystem::Pkcs11)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+
+    use ethers::{
+        core::{k256::elliptic_curve::sec1::ToEncodedPoint, types::TransactionRequest},
+        signers::LocalWallet,
+    };
+
+    #[test]
+    fn test_address_from_ec_point() {
+        let wallet = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let point = wallet.signer().verifying_key().to_encoded_point(false);
+
+        assert_eq!(
+            address_from_ec_point(point.as_bytes()),
+            Some(wallet.address()),
+            "raw point"
+        );
+
+        let der = [&[0x04, 0x41], point.as_bytes()].concat();
+        assert_eq!(
+            address_from_ec_point(&der),
+            Some(wallet.address()),
+            "DER encoded point"
+        );
+
+        let compressed = wallet.signer().verifying_key().to_encoded_point(true);
+        assert_eq!(
+            address_from_ec_point(compressed.as_bytes()),
+            None,
+            "compressed point"
+        );
+    }
+
+    #[test]
+    fn test_signature() {
+        let wallet = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let hash = H256::from(keccak256("message"));
+        let expected = wallet.sign_hash(hash);
+
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        expected.r.to_big_endian(&mut r);
+        expected.s.to_big_endian(&mut s);
+        assert_eq!(
+            signature(hash, &[r, s].concat(), wallet.address()).expect("low s signature"),
+            expected,
+            "low s signature"
+        );
+
+        // The signature with the high `s` is also valid, but its recovery ID is the other one.
+        (U256::from_big_endian(&SECP256K1_ORDER) - expected.s).to_big_endian(&mut s);
+        assert_eq!(
+            signature(hash, &[r, s].concat(), wallet.address()).expect("high s signature"),
+            expected,
+            "high s signature is normalized"
+        );
+
+        assert!(
+            signature(hash, &[r, s].concat(), Address::random()).is_err(),
+            "signature of another key"
+        );
+        assert!(
+            signature(hash, &r, wallet.address()).is_err(),
+            "invalid length"
+        );
+    }
+
+    #[test_with::env(
+        IPFS_CIDS_OWNERS_PKCS11_MODULE,
+        IPFS_CIDS_OWNERS_PKCS11_SLOT,
+        IPFS_CIDS_OWNERS_PKCS11_LABEL,
+        IPFS_CIDS_OWNERS_PKCS11_PIN
+    )]
+    #[tokio::test]
+    async fn test_pkcs11_signer() {
+        let var = |name: &str| {
+            env::var(name).unwrap_or_else(|_| {
+                panic!(
+                    "BUG this test should be ignored without the '{}' env var",
+                    name
+                )
+            })
+        };
+
+        let signer = Pkcs11Signer::new(
+            Path::new(&var("IPFS_CIDS_OWNERS_PKCS11_MODULE")),
+            var("IPFS_CIDS_OWNERS_PKCS11_SLOT")
+                .parse()
+                .expect("a valid slot ID"),
+            &var("IPFS_CIDS_OWNERS_PKCS11_LABEL"),
+            &var(ENV_PIN),
+        )
+        .expect("open the PKCS#11 key")
+        .with_chain_id(1337_u64);
+
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .value(1)
+            .nonce(0)
+            .gas(21000)
+            .gas_price(1)
+            .into();
+        let sig = signer
+            .sign_transaction(&tx)
+            .await
+            .expect("sign transaction");
+        let mut tx = tx;
+        tx.set_chain_id(1337_u64);
+        assert_eq!(
+            sig.recover(tx.sighash()).expect("recover signer"),
+            signer.address(),
+            "transaction signer"
+        );
+    }
+}
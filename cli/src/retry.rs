@@ -0,0 +1,294 @@
+//! Retry policy of the calls to the external systems (i.e. IPFS and Ethereum), so a transient
+//! failure, like a dropped connection or a rate limit, doesn't make the whole command fail.
+//!
+//! A call is attempted until it succeeds, it fails with an error that isn't transient, or the
+//! attempts are exhausted. Between attempts, it waits an exponential backoff with full jitter.
+//! Each attempt can time out, and all the attempts of a call together too. Which errors are
+//! transient depends on the external system, hence each one classifies its own errors.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use ethers::core::rand::{self, Rng};
+
+/// Maximum time waited between two attempts of a call.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How the calls to an external system are retried and timed out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts of a call, including the first one.
+    pub max_attempts: u32,
+    /// The backoff before the second attempt; it's doubled for each following attempt.
+    pub initial_backoff: Duration,
+    /// The time after which an attempt times out. Attempts don't time out when it's `None`.
+    pub call_timeout: Option<Duration>,
+    /// The time after which all the attempts of a call time out. Calls don't time out when it's
+    /// `None`.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            call_timeout: Some(Duration::from_secs(120)),
+            timeout: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Calls `call` until it succeeds, it fails with an error which isn't transient, or the
+    /// attempts or the time of the policy are exhausted.
+    /// `transient` classifies the failures of the attempts; it receives `None` when the attempt
+    /// timed out.
+    pub async fn retry<T, E, F, Fut>(
+        &self,
+        mut call: F,
+        transient: impl Fn(Option<&E>) -> bool,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let deadline = self.timeout.map(|t| Instant::now() + t);
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            let call_timeout = match (self.call_timeout, deadline) {
+                (Some(t), Some(d)) => Some(t.min(d.saturating_duration_since(Instant::now()))),
+                (t, d) => t.or_else(|| d.map(|d| d.saturating_duration_since(Instant::now()))),
+            };
+
+            // The error of the attempt is `None` when it timed out.
+            let last = match call_timeout {
+                Some(t) => match tokio::time::timeout(t, call()).await {
+                    Ok(Ok(v)) => return Ok(v),
+                    Ok(Err(err)) => Some(err),
+                    Err(_) => None,
+                },
+                None => match call().await {
+                    Ok(v) => return Ok(v),
+                    Err(err) => Some(err),
+                },
+            };
+
+            if !transient(last.as_ref()) {
+                return match last {
+                    Some(err) => Err(RetryError::Failed(err)),
+                    None => Err(RetryError::Exhausted { attempts, last }),
+                };
+            }
+
+            let backoff = self.backoff(attempts);
+            let expired = deadline.is_some_and(|d| Instant::now() + backoff >= d);
+            if attempts >= self.max_attempts || expired {
+                return Err(RetryError::Exhausted { attempts, last });
+            }
+
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    /// Returns the time to wait after the attempt number `attempt`, which is a random duration
+    /// between 0 and the exponential backoff (i.e. full jitter).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let max = self
+            .initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_BACKOFF);
+
+        max.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// The error of a call whose attempts didn't succeed.
+#[derive(Debug)]
+pub enum RetryError<E> {
+    /// The last attempt failed with an error which isn't transient.
+    Failed(E),
+    /// All the attempts failed with transient errors or timed out, or the time of the policy
+    /// expired. The last error is `None` when the last attempt timed out. Retrying the call later
+    /// may succeed.
+    Exhausted { attempts: u32, last: Option<E> },
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetryError::Failed(err) => err.fmt(f),
+            RetryError::Exhausted {
+                attempts,
+                last: Some(err),
+            } => write!(
+                f,
+                "the call failed with transient errors after {} attempts, the last one: {}",
+                attempts, err
+            ),
+            RetryError::Exhausted {
+                attempts,
+                last: None,
+            } => write!(
+                f,
+                "the call timed out after {} attempts, the last one didn't respond in time",
+                attempts
+            ),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for RetryError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RetryError::Failed(err) => Some(err),
+            RetryError::Exhausted { last, .. } => last
+                .as_ref()
+                .map(|e| e as &(dyn std::error::Error + 'static)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::Cell;
+
+    fn test_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            call_timeout: Some(Duration::from_millis(50)),
+            timeout: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let policy = test_policy();
+
+        let calls = Cell::new(0);
+        let res = policy
+            .retry(
+                || {
+                    calls.set(calls.get() + 1);
+                    let n = calls.get();
+                    async move {
+                        if n < 3 {
+                            Err("transient")
+                        } else {
+                            Ok(n)
+                        }
+                    }
+                },
+                |err| err == Some(&"transient"),
+            )
+            .await;
+        assert_eq!(res.ok(), Some(3), "succeeds in the last attempt");
+
+        calls.set(0);
+        let res: Result<(), _> = policy
+            .retry(
+                || {
+                    calls.set(calls.get() + 1);
+                    async { Err("permanent") }
+                },
+                |err| err == Some(&"transient"),
+            )
+            .await;
+        assert!(
+            matches!(res, Err(RetryError::Failed("permanent"))),
+            "permanent error"
+        );
+        assert_eq!(calls.get(), 1, "permanent errors aren't retried");
+
+        calls.set(0);
+        let res: Result<(), _> = policy
+            .retry(
+                || {
+                    calls.set(calls.get() + 1);
+                    async { Err("transient") }
+                },
+                |err| err == Some(&"transient"),
+            )
+            .await;
+        assert!(
+            matches!(
+                res,
+                Err(RetryError::Exhausted {
+                    attempts: 3,
+                    last: Some("transient")
+                })
+            ),
+            "attempts exhausted"
+        );
+        assert_eq!(calls.get(), 3, "all the attempts");
+    }
+
+    #[tokio::test]
+    async fn test_retry_timeouts() {
+        let policy = test_policy();
+        let slow = || async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok::<_, &str>(())
+        };
+
+        let res = policy.retry(slow, |err| err.is_none()).await;
+        assert!(
+            matches!(
+                res,
+                Err(RetryError::Exhausted {
+                    attempts: 3,
+                    last: None
+                })
+            ),
+            "all the attempts time out"
+        );
+
+        let res = policy.retry(slow, |_| false).await;
+        assert!(
+            matches!(
+                res,
+                Err(RetryError::Exhausted {
+                    attempts: 1,
+                    last: None
+                })
+            ),
+            "time outs aren't retried"
+        );
+
+        let policy = RetryPolicy {
+            max_attempts: 100,
+            call_timeout: None,
+            timeout: Some(Duration::from_millis(100)),
+            ..test_policy()
+        };
+        let start = Instant::now();
+        let res = policy.retry(slow, |err| err.is_none()).await;
+        assert!(
+            matches!(res, Err(RetryError::Exhausted { last: None, .. })),
+            "the call times out"
+        );
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "the call times out in time"
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(1),
+            ..test_policy()
+        };
+
+        for attempt in 1..10 {
+            let max = Duration::from_secs(2_u64.pow(attempt - 1)).min(MAX_BACKOFF);
+            assert!(policy.backoff(attempt) <= max, "attempt {}", attempt);
+        }
+        assert!(policy.backoff(u32::MAX) <= MAX_BACKOFF, "maximum backoff");
+    }
+}
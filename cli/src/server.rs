@@ -0,0 +1,405 @@
+//! HTTP server which exposes the operations of the application through a REST API.
+//!
+//! The API offers the following endpoints:
+//!
+//! * `POST /cids`: uploads the file sent in the `file` field of a multipart form to IPFS and
+//!   registers its CID. The form can have a `remote_path` field with the path to set for the
+//!   uploaded file, which must precede the `file` field because the file is uploaded as it's
+//!   received.
+//! * `GET /owners/:owner/cids?offset=<n>&limit=<n>`: lists the CIDs registered by `owner`.
+//! * `GET /txs/:hash`: shows the status of the transaction with `hash`.
+//!
+//! All the endpoints respond with JSON; errors are responded with an object with an `error` field,
+//! which contains the error as the command-line application outputs it in JSON format.
+//! When the server has an authentication token, the requests must send it as a bearer token in
+//! the `Authorization` header.
+
+use crate::{
+    cli, cmd,
+    error::{BoxError, Error},
+    ethereum, ipfs,
+    retry::RetryPolicy,
+};
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        multipart::{Field, MultipartError},
+        DefaultBodyLimit, Multipart, Path, Query, State as Extract,
+    },
+    http::{header, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use ethers::{
+    abi::AbiEncode,
+    core::types::{Address, TxHash},
+};
+use futures::{SinkExt, TryStreamExt};
+use ipfs_api_backend_hyper::IpfsClient;
+use serde::Deserialize;
+
+/// Number of chunks of an uploaded file which are buffered while they are sent to IPFS.
+const UPLOAD_BUFFER: usize = 16;
+
+/// The state shared by all the requests.
+pub struct State {
+    pub ipfs: IpfsClient,
+    pub cids_owners: ethereum::CIDsOwners,
+    /// The registration session of the owner of the CIDs registered through the server.
+    pub session: ethereum::Session,
+    /// The retry policy of the calls to IPFS.
+    pub retry: RetryPolicy,
+    pub tx: cli::TxArgs,
+    /// The token that the requests must send. No authentication is required when it's `None`.
+    pub auth_token: Option<String>,
+}
+
+/// Runs the server listening on `addr` until it fails. `max_upload_size` is the maximum size in
+/// bytes of the body of the upload requests.
+pub async fn run(state: State, addr: SocketAddr, max_upload_size: usize) -> Result<(), Error> {
+    axum::Server::try_bind(&addr)
+        .map_err(|err| {
+            Error::invalid_arguments("addr", &format!("cannot listen on '{}'. {}", addr, err))
+        })?
+        .serve(router(state, max_upload_size).into_make_service())
+        .await
+        .map_err(|err| Error::internal("HTTP server failure", BoxError::from(err)))
+}
+
+/// Creates the router with all the endpoints of the API.
+fn router(state: State, max_upload_size: usize) -> Router {
+    let state = Arc::new(state);
+
+    Router::new()
+        .route(
+            "/cids",
+            post(upload_and_register).layer(DefaultBodyLimit::max(max_upload_size)),
+        )
+        .route("/owners/:owner/cids", get(list))
+        .route("/txs/:hash", get(tx_status))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .with_state(state)
+}
+
+/// Rejects the requests which don't send the authentication token of the server.
+async fn authenticate<B>(
+    Extract(state): Extract<Arc<State>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if let Some(token) = &state.auth_token {
+        let authorized = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|t| constant_time_eq(t.as_bytes(), token.as_bytes()));
+
+        if !authorized {
+            let err = Error::invalid_arguments(
+                "authorization",
+                "missing or invalid authentication token",
+            );
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": err })),
+            )
+                .into_response();
+        }
+    }
+
+    next.run(req).await
+}
+
+/// Uploads the file of the request to IPFS and registers its CID.
+async fn upload_and_register(
+    Extract(state): Extract<Arc<State>>,
+    mut form: Multipart,
+) -> Result<Json<cmd::UploadRegisterSummary>, ApiError> {
+    let mut uploaded = None;
+    let mut remote_path = None;
+    while let Some(field) = form.next_field().await.map_err(invalid_form)? {
+        match field.name() {
+            Some("file") if uploaded.is_some() => {
+                return Err(ApiError(Error::invalid_arguments(
+                    "form{file}",
+                    "the file field must be sent only once",
+                )))
+            }
+            Some("file") => {
+                uploaded = Some(upload_field(&state, field, remote_path.as_deref()).await?)
+            }
+            Some("remote_path") if uploaded.is_some() => {
+                return Err(ApiError(Error::invalid_arguments(
+                    "form{remote_path}",
+                    "the remote_path field must precede the file field",
+                )))
+            }
+            Some("remote_path") => remote_path = Some(field.text().await.map_err(invalid_form)?),
+            _ => {}
+        }
+    }
+
+    let cid = uploaded
+        .ok_or_else(|| {
+            ApiError(Error::invalid_arguments(
+                "form{file}",
+                "the file field is required",
+            ))
+        })?
+        .cid;
+
+    let ether_tx_hash = cmd::register_cid(&state.session, &cid, &state.tx).await?;
+
+    Ok(Json(cmd::UploadRegisterSummary {
+        cid,
+        ether_tx_hash,
+        pending: state.tx.no_wait,
+        journal: None,
+        children: Vec::new(),
+    }))
+}
+
+/// Query parameters for paginating the list of CIDs.
+#[derive(Deserialize)]
+struct Page {
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Lists the CIDs registered by the owner of the request path.
+async fn list(
+    Extract(state): Extract<Arc<State>>,
+    Path(owner): Path<String>,
+    Query(page): Query<Page>,
+) -> Result<Json<cmd::ListSummary>, ApiError> {
+    let addr = owner.parse::<Address>().map_err(|err| {
+        Error::invalid_arguments(
+            "owner",
+            &format!("invalid format for Ethereum address. {}", err),
+        )
+    })?;
+
+    let cids = state.cids_owners.registered_cids(addr).await?;
+    let total = cids.len();
+
+    Ok(Json(cmd::ListSummary {
+        owner,
+        total,
+        offset: page.offset,
+        cids: cmd::paginate(cids, page.offset, page.limit),
+    }))
+}
+
+/// Shows the status of the transaction of the request path.
+async fn tx_status(
+    Extract(state): Extract<Arc<State>>,
+    Path(hash): Path<String>,
+) -> Result<Json<cmd::TxStatusSummary>, ApiError> {
+    let tx_hash = hash.parse::<TxHash>().map_err(|err| {
+        Error::invalid_arguments(
+            "hash",
+            &format!("invalid format for Ethereum transaction hash. {}", err),
+        )
+    })?;
+
+    let status = state.cids_owners.transaction_status(tx_hash).await?;
+    Ok(Json(cmd::TxStatusSummary::new(
+        tx_hash.encode_hex(),
+        status,
+    )))
+}
+
+/// Uploads the content of the multipart `field` to IPFS as it's received, without buffering it.
+/// The chunks are forwarded to the IPFS client through a channel because the field cannot be
+/// shared between threads, which the IPFS client requires.
+async fn upload_field(
+    state: &State,
+    mut field: Field<'_>,
+    remote_path: Option<&str>,
+) -> Result<ipfs::UploadedFile, Error> {
+    let (mut chunks, rx) = futures::channel::mpsc::channel(UPLOAD_BUFFER);
+    let forward = async move {
+        loop {
+            match field.chunk().await {
+                Ok(Some(c)) => {
+                    if chunks.send(Ok(c)).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                Ok(None) => return Ok(()),
+                Err(err) => {
+                    // Fail the upload rather than ending the content, which would upload it
+                    // truncated.
+                    let _ = chunks
+                        .send(Err(std::io::Error::other(err.to_string())))
+                        .await;
+                    return Err(invalid_form(err).0);
+                }
+            }
+        }
+    };
+
+    let client = ipfs::Client::with_client(&state.ipfs, state.retry);
+    let upload = client.upload_data(rx.into_async_read(), remote_path);
+    let (forwarded, uploaded) = futures::join!(forward, upload);
+    // The failure of the form is the cause of the failed upload, so it's the one reported.
+    forwarded?;
+    uploaded
+}
+
+/// Returns the error of an invalid multipart form.
+fn invalid_form(err: MultipartError) -> ApiError {
+    ApiError(Error::invalid_arguments(
+        "form",
+        &format!("invalid multipart form. {}", err),
+    ))
+}
+
+/// Wraps the errors returned by the endpoints for responding them with the status code
+/// corresponding to their kind.
+struct ApiError(Error);
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            Error::InvalidArguments(_) => StatusCode::BAD_REQUEST,
+            Error::External(_) => StatusCode::BAD_GATEWAY,
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status, Json(serde_json::json!({ "error": self.0 }))).into_response()
+    }
+}
+
+/// Compares `a` and `b` in a time which doesn't depend on how many bytes they have in common for
+/// not leaking the authentication token through timing attacks.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use axum::body::Body;
+    use mktemp::Temp;
+    use tower::ServiceExt;
+
+    /// Returns the state of the server and the temporary directory of the owner's lock, which must
+    /// be kept while the server runs.
+    fn test_state(auth_token: Option<&str>) -> (State, Temp) {
+        let cids_owners = ethereum::CIDsOwners::new(
+            Address::zero(),
+            "http://localhost:8545",
+            None,
+            RetryPolicy::default(),
+        )
+        .expect("create CIDsOwners");
+        let owner = ethers::signers::LocalWallet::new(&mut ethers::core::rand::thread_rng());
+        let (session, dir) = ethereum::test::test_session(&cids_owners, owner.into());
+
+        let state = State {
+            ipfs: IpfsClient::default(),
+            session,
+            cids_owners,
+            retry: RetryPolicy::default(),
+            tx: cli::TxArgs {
+                no_wait: false,
+                confirmation: cli::ConfirmationArgs {
+                    confirmations: 1,
+                    finalized: false,
+                },
+            },
+            auth_token: auth_token.map(String::from),
+        };
+        (state, dir)
+    }
+
+    #[tokio::test]
+    async fn test_authentication() {
+        let (state, _dir) = test_state(Some("secret"));
+        let app = router(state, 1024);
+
+        for (auth, desc) in [
+            (None, "missing token"),
+            (Some("Bearer other"), "wrong token"),
+            (Some("secret"), "not bearer token"),
+        ] {
+            let mut req = Request::builder().uri("/owners/invalid/cids");
+            if let Some(a) = auth {
+                req = req.header(header::AUTHORIZATION, a);
+            }
+
+            let res = app
+                .clone()
+                .oneshot(req.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{}", desc);
+        }
+
+        let req = Request::builder()
+            .uri("/owners/invalid/cids")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "valid token");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        let (state, _dir) = test_state(None);
+        let app = router(state, 1024);
+
+        for uri in ["/owners/0x01/cids", "/txs/0x01"] {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/cids")
+            .header(header::CONTENT_TYPE, "multipart/form-data; boundary=X")
+            .body(Body::from(
+                "--X\r\nContent-Disposition: form-data; name=\"remote_path\"\r\n\r\n/a.txt\r\n--X--\r\n",
+            ))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "missing file");
+
+        let mut body = Vec::new();
+        let mut res_body = res.into_body();
+        while let Some(chunk) = axum::body::HttpBody::data(&mut res_body).await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("JSON error");
+        assert_eq!(body["error"]["kind"], "InvalidArguments", "error kind");
+        assert_eq!(body["error"]["names"], "form{file}", "error names");
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"), "equal");
+        assert!(!constant_time_eq(b"secret", b"secreT"), "different");
+        assert!(!constant_time_eq(b"secret", b"secret1"), "different length");
+    }
+}
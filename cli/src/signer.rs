@@ -0,0 +1,274 @@
+//! Remote signer which signs the transactions of the CIDs' owners through the HTTP API of a
+//! [Web3Signer](https://docs.web3signer.consensys.net) compatible service, so the private keys
+//! aren't on the machine that runs the application.
+//!
+//! The signer only uses the `POST /api/v1/eth1/sign/{identifier}` endpoint, which receives the
+//! data to sign as `{"data": "0x..."}` and responds with the hex encoded signature of its
+//! Keccak-256 hash.
+
+use crate::error::{BoxError, Error, ExternalSystem};
+
+use ethers::{
+    core::types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Address, Signature,
+    },
+    signers::{to_eip155_v, Signer},
+    utils::hex,
+};
+
+/// Prefix of the messages signed following EIP-191.
+const MESSAGE_PREFIX: &str = "\x19Ethereum Signed Message:\n";
+
+/// Signs with the key identified by its address held by a remote signer.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    /// The URL of the sign endpoint of the key.
+    sign_url: String,
+    address: Address,
+    chain_id: u64,
+}
+
+impl RemoteSigner {
+    /// Creates a new instance for the key of `address` of the remote signer of `endpoint`.
+    /// The chain ID is 1 (i.e. Ethereum mainnet) until it's set with [`Signer::with_chain_id`].
+    pub fn new(endpoint: &str, address: Address) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            sign_url: format!(
+                "{}/api/v1/eth1/sign/{:?}",
+                endpoint.trim_end_matches('/'),
+                address
+            ),
+            address,
+            chain_id: 1,
+        }
+    }
+
+    /// Requests the remote signer to sign the Keccak-256 hash of `data`. The `v` of the returned
+    /// signature is 27 or 28; any `v` of the remote signer other than 0, 1, 27 or 28 (e.g. an
+    /// EIP-155 one) is an error.
+    async fn sign_data(&self, data: &[u8]) -> Result<Signature, Error> {
+        let map_err =
+            |err: reqwest::Error| Error::external(BoxError::from(err), ExternalSystem::Signer);
+
+        let res = self
+            .client
+            .post(&self.sign_url)
+            .json(&serde_json::json!({ "data": format!("0x{}", hex::encode(data)) }))
+            .send()
+            .await
+            .map_err(map_err)?;
+
+        let status = res.status();
+        let body = res.text().await.map_err(map_err)?;
+        if !status.is_success() {
+            return Err(Error::external(
+                BoxError::from(format!(
+                    "signing request failed with status {}. {}",
+                    status,
+                    body.trim()
+                )),
+                ExternalSystem::Signer,
+            ));
+        }
+
+        let mut sig = body
+            .trim()
+            .trim_matches('"')
+            .parse::<Signature>()
+            .map_err(|err| Error::external(BoxError::from(err), ExternalSystem::Signer))?;
+
+        // Some signers return the recovery ID as `v` rather than the recovery ID plus 27.
+        sig.v = match sig.v {
+            0 | 1 => sig.v + 27,
+            27 | 28 => sig.v,
+            v => {
+                return Err(Error::external(
+                    BoxError::from(format!(
+                        "the signature has an invalid v {}, it must be 0, 1, 27 or 28",
+                        v
+                    )),
+                    ExternalSystem::Signer,
+                ))
+            }
+        };
+
+        Ok(sig)
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for RemoteSigner {
+    type Error = Error;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        let message = message.as_ref();
+        let mut data = format!("{}{}", MESSAGE_PREFIX, message.len()).into_bytes();
+        data.extend_from_slice(message);
+
+        self.sign_data(&data).await
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        // The RLP encoding of the transaction must have the same chain ID as `v` of the
+        // signature.
+        let mut tx = tx.clone();
+        let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(self.chain_id);
+        tx.set_chain_id(chain_id);
+
+        let mut sig = self.sign_data(&tx.rlp()).await?;
+        let recovery_id = match sig.v {
+            27 => 0,
+            28 => 1,
+            v => {
+                return Err(Error::internal(
+                    "BUG sign_data should return a signature whose v is 27 or 28",
+                    BoxError::from(format!("v is {}", v)),
+                ))
+            }
+        };
+        sig.v = to_eip155_v(recovery_id, chain_id);
+        Ok(sig)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        _payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        // The sign endpoint always hashes the data, so it cannot sign an EIP-712 hash.
+        Err(Error::invalid_arguments(
+            "payload",
+            "the remote signer doesn't support signing EIP-712 typed data",
+        ))
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use axum::{extract, http::StatusCode, routing::post, Router};
+    use ethers::{
+        core::types::{TransactionRequest, H256},
+        signers::LocalWallet,
+        utils::keccak256,
+    };
+
+    /// Starts a stand-in of a Web3Signer service which holds the key of `wallet` and returns its
+    /// endpoint.
+    fn start_signer(wallet: LocalWallet) -> String {
+        let identifier = format!("{:?}", wallet.address());
+        let app = Router::new().route(
+            "/api/v1/eth1/sign/:identifier",
+            post(
+                move |extract::Path(id): extract::Path<String>,
+                      extract::Json(body): extract::Json<serde_json::Value>| async move {
+                    if id != identifier {
+                        return (StatusCode::NOT_FOUND, String::from("key not found"));
+                    }
+
+                    let data = body["data"].as_str().unwrap_or_default();
+                    let data = hex::decode(data.trim_start_matches("0x")).expect("hex data");
+                    let sig = wallet.sign_hash(H256::from(keccak256(data)));
+                    (StatusCode::OK, format!("0x{}", sig))
+                },
+            ),
+        );
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind signer listener");
+        let addr = listener.local_addr().expect("signer listener address");
+        let server = axum::Server::from_tcp(listener)
+            .expect("signer server")
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer() {
+        let wallet =
+            LocalWallet::new(&mut ethers::core::rand::thread_rng()).with_chain_id(1337_u64);
+        let endpoint = start_signer(wallet.clone());
+        let signer = RemoteSigner::new(&endpoint, wallet.address()).with_chain_id(1337_u64);
+
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .value(1)
+            .nonce(3)
+            .gas(21000)
+            .gas_price(1)
+            .into();
+        let sig = signer
+            .sign_transaction(&tx)
+            .await
+            .expect("sign transaction");
+        assert_eq!(
+            sig,
+            wallet.sign_transaction(&tx).await.expect("sign locally"),
+            "transaction signature"
+        );
+
+        let sig = signer.sign_message("hello").await.expect("sign message");
+        assert_eq!(
+            sig.recover("hello").expect("recover signer"),
+            wallet.address(),
+            "message signer"
+        );
+
+        let unknown = RemoteSigner::new(&endpoint, Address::random());
+        assert!(
+            matches!(unknown.sign_message("hello").await, Err(Error::External(_))),
+            "unknown key"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_invalid_v() {
+        let address = Address::random();
+        // A signature with an EIP-155 `v` of the chain ID 1.
+        let app = Router::new().route(
+            "/api/v1/eth1/sign/:identifier",
+            post(|| async { format!("0x{}25", "11".repeat(64)) }),
+        );
+        let server =
+            axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let endpoint = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let signer = RemoteSigner::new(&endpoint, address);
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .value(1)
+            .nonce(3)
+            .gas(21000)
+            .gas_price(1)
+            .into();
+        assert!(
+            matches!(signer.sign_transaction(&tx).await, Err(Error::External(_))),
+            "EIP-155 v of the transaction signature"
+        );
+        assert!(
+            matches!(signer.sign_message("hello").await, Err(Error::External(_))),
+            "EIP-155 v of the message signature"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! State of the watch mode, which records the files of the watched directory that have been
+//! already uploaded and registered, so they aren't registered again when the application restarts.
+
+use crate::error::{BoxError, Error};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind as ioErrorKind;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the file where the state is stored by default. It's placed in the watched directory.
+pub const STATE_FILENAME: &str = ".ipfs-cids-owners-watch.json";
+
+/// The files that have been uploaded and registered indexed by their path relative to the watched
+/// directory.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct State {
+    files: BTreeMap<String, Entry>,
+}
+
+/// The registration of the last uploaded content of a file.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Entry {
+    pub cid: String,
+    pub ether_tx_hash: String,
+    /// Whether the transaction was sent without waiting until it's mined. It may be dropped, so
+    /// its status is checked when the application starts.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pending: bool,
+}
+
+impl State {
+    /// Loads the state stored in `path`. It returns an empty state if the file doesn't exist.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let content = match fs::read(path) {
+            Ok(c) => c,
+            Err(err) if err.kind() == ioErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(Error::internal(
+                    "system error when reading the watch state file",
+                    BoxError::from(err),
+                ))
+            }
+        };
+
+        serde_json::from_slice(&content).map_err(|err| {
+            Error::invalid_arguments("path", &format!("invalid watch state file. {}", err))
+        })
+    }
+
+    /// Stores the state in `path`.
+    /// The state is written to a temporary file which is renamed afterwards for not corrupting
+    /// the stored state if the application stops in the middle of the write.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let internal =
+            |err: BoxError| Error::internal("system error when writing the watch state file", err);
+
+        let content = serde_json::to_vec_pretty(self).map_err(|err| internal(err.into()))?;
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, content).map_err(|err| internal(err.into()))?;
+        fs::rename(&tmp_path, path).map_err(|err| internal(err.into()))
+    }
+
+    /// Returns the registration of the file with `path` relative to the watched directory.
+    pub fn get(&self, path: &str) -> Option<&Entry> {
+        self.files.get(path)
+    }
+
+    /// Sets the registration of the file with `path` relative to the watched directory.
+    pub fn insert(&mut self, path: String, entry: Entry) {
+        self.files.insert(path, entry);
+    }
+
+    /// Removes the registration of the file with `path` relative to the watched directory, so it's
+    /// registered again.
+    pub fn remove(&mut self, path: &str) -> Option<Entry> {
+        self.files.remove(path)
+    }
+
+    /// Returns the pending registrations indexed by the path of their files relative to the
+    /// watched directory.
+    pub fn pending(&self) -> Vec<(String, Entry)> {
+        self.files
+            .iter()
+            .filter(|(_, e)| e.pending)
+            .map(|(p, e)| (p.clone(), e.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use mktemp::Temp;
+
+    #[test]
+    fn test_state_load_save() {
+        let dir = Temp::new_dir().expect("create temp dir");
+        let path = dir.join(STATE_FILENAME);
+
+        let mut state = State::load(&path).expect("load a state which doesn't exist");
+        assert_eq!(state, State::default(), "empty state");
+
+        let entry = Entry {
+            cid: String::from("QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u"),
+            ether_tx_hash: String::from("0x01"),
+            pending: false,
+        };
+        let pending = Entry {
+            ether_tx_hash: String::from("0x02"),
+            pending: true,
+            ..entry.clone()
+        };
+        state.insert(String::from("sub/a.txt"), entry.clone());
+        state.insert(String::from("c.txt"), pending.clone());
+        state.save(&path).expect("save the state");
+
+        let mut loaded = State::load(&path).expect("load the saved state");
+        assert_eq!(loaded.get("sub/a.txt"), Some(&entry), "saved entry");
+        assert_eq!(loaded.get("c.txt"), Some(&pending), "saved pending entry");
+        assert_eq!(loaded.get("b.txt"), None, "not saved entry");
+        assert_eq!(
+            loaded.pending(),
+            vec![(String::from("c.txt"), pending.clone())],
+            "pending entries"
+        );
+
+        assert_eq!(loaded.remove("c.txt"), Some(pending), "removed entry");
+        assert!(loaded.pending().is_empty(), "no pending entries");
+    }
+}
@@ -14,7 +14,7 @@ fn main() {
     // Generate the Rust bindings for the smart contracts.
     Abigen::new(
         "CIDsOwners",
-        &smart_contract_dir.join("CIDsOwners.json").to_string_lossy(),
+        smart_contract_dir.join("CIDsOwners.json").to_string_lossy(),
     )
     .expect("create builder ABI JSON contract file")
     .generate()
@@ -1,3 +1,6 @@
+// The generated code mixes inner and outer attributes in the same module.
+#![allow(clippy::mixed_attributes_style)]
+
 // Include the generated Rust file of the CIDsOwners JSON ABI file by the ABI generator of the
 // `ethers` crate.
 include!(concat!(env!("OUT_DIR"), "/cids_owners.rs"));
@@ -0,0 +1,10 @@
+fn main() {
+    // Use the vendored protobuf compiler for not requiring to have it installed in the system.
+    std::env::set_var(
+        "PROTOC",
+        protoc_bin_vendored::protoc_bin_path().expect("vendored protoc binary for this platform"),
+    );
+
+    tonic_build::compile_protos("proto/cids_owners.proto")
+        .expect("generate the Rust code of the CIDsOwners gRPC service (cids_owners.proto)");
+}
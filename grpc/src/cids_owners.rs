@@ -0,0 +1,3 @@
+// Include the generated Rust file of the cids_owners.proto file by the protobuf compiler of the
+// `tonic-build` crate.
+tonic::include_proto!("ipfs_cids_owners.v1");
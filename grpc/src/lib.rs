@@ -0,0 +1,77 @@
+//! This crate generates the Rust code of the gRPC service for uploading files to IPFS and
+//! registering their CIDs to the CIDsOwners smart contract, which is defined in the
+//! `proto/cids_owners.proto` file.
+//!
+//! It contains the server trait, which the command-line application implements, and the client
+//! for connecting to it.
+
+pub mod cids_owners;
+
+use cids_owners::{upload_request::Content, UploadMetadata, UploadRequest};
+
+use std::io;
+
+use futures::{
+    io::{AsyncRead, AsyncReadExt},
+    stream::{self, Stream, StreamExt},
+};
+
+/// Size of the chunks in which [`upload_requests`] splits the content of the files.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Returns the stream of requests for uploading the content read from `data` with the `Upload`
+/// and `UploadAndRegister` methods of the client.
+///
+/// The stream ends when `data` is fully read or after yielding the error of a failed read. Pass it
+/// through [`abort_on_error`] for sending it with the client.
+pub fn upload_requests<R>(
+    data: R,
+    remote_path: Option<String>,
+) -> impl Stream<Item = io::Result<UploadRequest>> + Send + 'static
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    let metadata = UploadRequest {
+        content: Some(Content::Metadata(UploadMetadata { remote_path })),
+    };
+
+    let chunks = stream::unfold(Some(data), |data| async move {
+        let mut data = data?;
+        let mut buf = vec![0; CHUNK_SIZE];
+        match data.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((
+                    Ok(UploadRequest {
+                        content: Some(Content::Chunk(buf)),
+                    }),
+                    Some(data),
+                ))
+            }
+            Err(err) => Some((Err(err), None)),
+        }
+    });
+
+    stream::iter([Ok(metadata)]).chain(chunks)
+}
+
+/// Returns the stream of `requests` up to its first error, which is replaced by a request that
+/// aborts the upload, so the server fails it rather than uploading a truncated file.
+pub fn abort_on_error<S>(requests: S) -> impl Stream<Item = UploadRequest> + Send + 'static
+where
+    S: Stream<Item = io::Result<UploadRequest>> + Send + 'static,
+{
+    stream::unfold(Some(requests.boxed()), |requests| async move {
+        let mut requests = requests?;
+        match requests.next().await? {
+            Ok(req) => Some((req, Some(requests))),
+            Err(err) => Some((
+                UploadRequest {
+                    content: Some(Content::Abort(err.to_string())),
+                },
+                None,
+            )),
+        }
+    })
+}